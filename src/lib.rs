@@ -3,13 +3,46 @@ pub mod utils;
 #[cfg(test)]
 mod tests {
 
-    use crate::utils::constants::ADDRESS_BABE1;
+    use crate::utils::constants::{ADDRESS_BABE1, SUBMISSION_BODY_LIMIT};
     use crate::utils::core::bundle::Bundle;
+    use crate::utils::core::bundle_data::BundleData;
+    use crate::utils::core::bundle_validator::{BundleValidator, Violation};
+    use crate::utils::core::chunk_dispatcher::ChunkDispatcher;
+    use crate::utils::core::chunker_scheduler::{ChunkerPool, SchedulerPolicy};
     use crate::utils::core::envelope::Envelope;
+    use crate::utils::core::inclusion::{wait_for_inclusion, Inclusion, InclusionPolicy};
+    use crate::utils::core::keystore_index::KeystoreIndex;
     use crate::utils::core::large_bundle::LargeBundle;
-    use crate::utils::core::super_account::SuperAccount;
+    use crate::utils::core::large_bundle_manifest::{
+        parse_manifest, ChunkEntry, LargeBundleManifest, ManifestPart,
+    };
+    use crate::utils::core::large_bundle_source::LargeBundleSource;
+    use crate::utils::core::signed_bundle::BundleFees;
+    use crate::utils::core::super_account::{KeystoreRetention, SuperAccount};
     use crate::utils::core::tags::Tag;
-    use crate::utils::evm::{generate_random_bytes, generate_random_calldata};
+    use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+    use crate::utils::core::upload_journal::UploadJournal;
+    use crate::utils::core::verified_bundle::{VerificationMode, VerifiedBundleData};
+    use crate::utils::errors::Error;
+    use crate::utils::evm::{
+        broadcast_bundle, create_envelope, create_evm_http_client, generate_random_calldata,
+        retrieve_bundle_data,
+    };
+    use crate::utils::fees::{calldata_gas, FeePolicy};
+    use crate::utils::network::NetworkConfig;
+    use crate::utils::nonce_manager::{NonceGuard, NonceManager};
+    use crate::utils::remote_signer::{SignHashRequest, SignHashResponse};
+    use crate::utils::server::range::{
+        parse_range_header, range_segments, ByteRange, RangeRequest, Segment,
+    };
+    use crate::utils::server::router;
+    use crate::utils::server::state::AppState;
+    use crate::utils::signer::BundlerSigner;
+    use alloy::consensus::{Transaction, TxEnvelope};
+    use alloy::eips::eip2718::Decodable2718;
+    use alloy::primitives::{Address, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_bundle_retrieval() {
@@ -235,4 +268,2066 @@ mod tests {
         // println!("LARGE BUNDLE: {:?}", large_bundle);
         assert_ne!(bundle_hash.len(), 0);
     }
+
+    #[test]
+    fn test_network_config_from_toml() {
+        let config = NetworkConfig::from_toml_str(
+            r#"
+            name = "local"
+            rpc_url = "http://127.0.0.1:8545"
+            chain_id = 31337
+            babe1 = "0xbabe1d25501157043c7b4ea7CBC877B9B4D8A057"
+            babe2 = "0xbabe2dCAf248F2F1214dF2a471D77bC849a2Ce84"
+
+            [gas]
+            bundle_gas_limit = 30000000
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.chain_id, 31337);
+        assert_eq!(config.gas.bundle_gas_limit, 30_000_000);
+        // unspecified gas fields fall back to the alphanet defaults
+        assert_eq!(
+            config.gas.max_fee_per_gas,
+            NetworkConfig::alphanet().gas.max_fee_per_gas
+        );
+        assert!(NetworkConfig::preset("unknown").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_bundle_data_errors() {
+        let not_hex = retrieve_bundle_data("0xzz".to_string()).await;
+        assert!(matches!(not_hex, Err(Error::InvalidBundleHex(_))));
+
+        let not_brotli = retrieve_bundle_data("0xffffffffffffffff".to_string()).await;
+        assert!(matches!(not_brotli, Err(Error::BrotliDecompression(_))));
+
+        let garbage = TxEnvelopeWrapper::brotli_compress(b"not a borsh bundle");
+        let not_borsh = retrieve_bundle_data(hex::encode(garbage)).await;
+        assert!(matches!(not_borsh, Err(Error::BorshDeserialization(_))));
+
+        let bundle = BundleData::from(vec![
+            TxEnvelopeWrapper::default(),
+            TxEnvelopeWrapper {
+                nonce: 1,
+                ..Default::default()
+            },
+        ]);
+        let compressed = TxEnvelopeWrapper::brotli_compress(&TxEnvelopeWrapper::borsh_ser(&bundle));
+        let violation = retrieve_bundle_data(hex::encode(compressed)).await;
+        assert!(matches!(
+            violation,
+            Err(Error::EnvelopeRuleViolation { index: 1, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bundle_validator_report() {
+        let private_key = "6f142508b4eea641e33cb2a0161221105086a84584c74245ca463a49effea30b";
+        let network = NetworkConfig::default();
+        let envelope = Envelope::new()
+            .data(Some("hello world".as_bytes().to_vec()))
+            .target(None)
+            .build()
+            .unwrap();
+        let tx = create_envelope(
+            &BundlerSigner::from_private_key(private_key).unwrap(),
+            envelope.clone(),
+            &network,
+        )
+        .await
+        .unwrap();
+        let wrapper = TxEnvelopeWrapper::from_envelope(tx, envelope);
+
+        let mut tampered = wrapper.clone();
+        tampered.value = "1".to_string();
+
+        let bundle = BundleData::from(vec![wrapper.clone(), tampered]);
+        let report = BundleValidator::new(&network).validate(&bundle, Some(1024));
+
+        assert!(!report.valid);
+        assert!(report.envelopes[0].violations.is_empty());
+        let signer: PrivateKeySigner = private_key.parse().unwrap();
+        assert_eq!(
+            report.envelopes[0].signer,
+            Some(signer.address().to_checksum(Some(network.chain_id)))
+        );
+        assert_eq!(
+            report.envelopes[0].computed_hash.as_deref(),
+            Some(wrapper.hash.as_str())
+        );
+        let violations = &report.envelopes[1].violations;
+        assert!(violations.contains(&Violation::NonZeroValue {
+            value: "1".to_string()
+        }));
+        assert!(violations
+            .iter()
+            .any(|v| matches!(v, Violation::HashMismatch { .. })));
+        assert!(matches!(
+            report.into_result(),
+            Err(Error::EnvelopeRuleViolation { index: 1, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_build_signed_bundle_offline() {
+        let private_key =
+            String::from("6f142508b4eea641e33cb2a0161221105086a84584c74245ca463a49effea30b");
+        let network = NetworkConfig::default();
+        let envelope = Envelope::new()
+            .data(Some("hello world".as_bytes().to_vec()))
+            .target(None)
+            .build()
+            .unwrap();
+
+        let signed = Bundle::new()
+            .private_key(private_key.clone())
+            .envelopes(vec![envelope])
+            .build()
+            .unwrap()
+            .build_signed(7, BundleFees::from(&network.gas))
+            .await
+            .unwrap();
+
+        let tx = TxEnvelope::decode_2718(&mut signed.raw_tx.as_slice()).unwrap();
+        let signer: PrivateKeySigner = private_key.parse().unwrap();
+        assert_eq!(tx.to(), Some(network.babe1.parse().unwrap()));
+        assert_eq!(tx.nonce(), 7);
+        assert_eq!(tx.chain_id(), Some(network.chain_id));
+        assert_eq!(tx.input().to_vec(), signed.encoded.compressed);
+        assert_eq!(tx.recover_signer().unwrap(), signer.address());
+        assert_eq!(tx.tx_hash().to_string(), signed.tx_hash);
+        assert_eq!(
+            TxEnvelopeWrapper::borsh_der(
+                TxEnvelopeWrapper::brotli_decompress(signed.encoded.compressed).unwrap()
+            )
+            .unwrap(),
+            signed.encoded.bundle
+        );
+
+        // the same envelopes, signed for 0xbabe2
+        let babe2 = Bundle::new()
+            .private_key(private_key)
+            .envelopes(vec![Envelope::new()
+                .data(Some("hello world".as_bytes().to_vec()))
+                .target(None)
+                .build()
+                .unwrap()])
+            .build()
+            .unwrap()
+            .build_signed_to(&network.babe2, 7, BundleFees::from(&network.gas))
+            .await
+            .unwrap();
+        let tx = TxEnvelope::decode_2718(&mut babe2.raw_tx.as_slice()).unwrap();
+        assert_eq!(tx.to(), Some(network.babe2.parse().unwrap()));
+        assert_eq!(babe2.encoded.bundle, signed.encoded.bundle);
+    }
+
+    // local stand-in for a remote signing service, backed by a local key
+    fn mock_router(signer: PrivateKeySigner) -> axum::Router {
+        use alloy::signers::SignerSync;
+        use axum::extract::State;
+        use axum::http::StatusCode;
+        use axum::Json;
+
+        async fn sign_hash(
+            State(signer): State<PrivateKeySigner>,
+            Json(request): Json<SignHashRequest>,
+        ) -> Result<Json<SignHashResponse>, StatusCode> {
+            let address: Address = request
+                .address
+                .parse()
+                .map_err(|_| StatusCode::BAD_REQUEST)?;
+            if address != signer.address() {
+                return Err(StatusCode::FORBIDDEN);
+            }
+
+            let hash: alloy::primitives::B256 =
+                request.hash.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+            let signature = signer
+                .sign_hash_sync(&hash)
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            Ok(Json(SignHashResponse {
+                signature: format!("0x{}", hex::encode(signature.as_bytes())),
+            }))
+        }
+
+        axum::Router::new()
+            .route("/sign", axum::routing::post(sign_hash))
+            .with_state(signer)
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_with_separate_payer() {
+        let envelope_key: PrivateKeySigner =
+            "6f142508b4eea641e33cb2a0161221105086a84584c74245ca463a49effea30b"
+                .parse()
+                .unwrap();
+        let envelope_owner = envelope_key.address();
+        let payer = PrivateKeySigner::random();
+        let payer_address = payer.address();
+
+        // local mock of the remote signing service
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, mock_router(envelope_key))
+                .await
+                .unwrap();
+        });
+
+        let envelope = Envelope::new()
+            .data(Some("hello world".as_bytes().to_vec()))
+            .build()
+            .unwrap();
+        let network = NetworkConfig::default();
+
+        let signed = Bundle::new()
+            .signer(BundlerSigner::remote(url, envelope_owner))
+            .payer(BundlerSigner::new(payer))
+            .envelopes(vec![envelope])
+            .build()
+            .unwrap()
+            .build_signed(0, BundleFees::from(&network.gas))
+            .await
+            .unwrap();
+
+        let report = BundleValidator::new(&network).validate(&signed.encoded.bundle, None);
+        assert!(report.valid);
+        assert_eq!(
+            report.envelopes[0].signer,
+            Some(envelope_owner.to_checksum(Some(network.chain_id)))
+        );
+
+        let tx = TxEnvelope::decode_2718(&mut signed.raw_tx.as_slice()).unwrap();
+        assert_eq!(tx.recover_signer().unwrap(), payer_address);
+    }
+
+    #[tokio::test]
+    async fn test_settle_presigned_envelopes() {
+        let network = NetworkConfig::default();
+        let owners = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let settler = BundlerSigner::new(PrivateKeySigner::random());
+
+        // each owner signs its envelope on its own side
+        let mut presigned = Vec::new();
+        for (i, owner) in owners.iter().enumerate() {
+            let envelope = Envelope::new()
+                .data(Some(format!("envelope {}", i).into_bytes()))
+                .build()
+                .unwrap();
+            let tx = create_envelope(&BundlerSigner::new(owner.clone()), envelope, &network)
+                .await
+                .unwrap();
+            presigned.push(TxEnvelopeWrapper::try_from_tx_envelope(tx, None).unwrap());
+        }
+
+        let signed = Bundle::new()
+            .payer(settler.clone())
+            .signed_envelopes(presigned.clone())
+            .build()
+            .unwrap()
+            .build_signed(0, BundleFees::from(&network.gas))
+            .await
+            .unwrap();
+
+        let report = BundleValidator::new(&network).validate(&signed.encoded.bundle, None);
+        assert!(report.valid);
+        for (report, owner) in report.envelopes.iter().zip(owners.iter()) {
+            assert_eq!(
+                report.signer,
+                Some(owner.address().to_checksum(Some(network.chain_id)))
+            );
+        }
+        let tx = TxEnvelope::decode_2718(&mut signed.raw_tx.as_slice()).unwrap();
+        assert_eq!(tx.recover_signer().unwrap(), settler.address());
+
+        // a tampered envelope is rejected before being settled
+        let mut tampered = presigned[1].clone();
+        tampered.input = "0xdeadbeef".to_string();
+        let rejected = Bundle::new()
+            .payer(settler)
+            .add_signed_envelope(presigned[0].clone())
+            .add_signed_envelope(tampered)
+            .build();
+        assert!(matches!(
+            rejected,
+            Err(Error::EnvelopeRuleViolation { index: 1, .. })
+        ));
+
+        // only EIP-155 signed legacy txs are envelopes, a 4xx for the server
+        use alloy::consensus::{SignableTransaction, TxEip1559, TxLegacy};
+        use alloy::network::TxSignerSync;
+        let mut typed = TxEip1559 {
+            chain_id: network.chain_id,
+            ..Default::default()
+        };
+        let signature = owners[0].sign_transaction_sync(&mut typed).unwrap();
+        let mut unprotected = TxLegacy::default();
+        let unprotected_signature = owners[0].sign_transaction_sync(&mut unprotected).unwrap();
+        for tx in [
+            TxEnvelope::from(typed.into_signed(signature)),
+            TxEnvelope::from(unprotected.into_signed(unprotected_signature)),
+        ] {
+            let error = TxEnvelopeWrapper::try_from_tx_envelope(tx, None).unwrap_err();
+            assert!(matches!(error, Error::EnvelopeVerification(_)));
+            assert_eq!(
+                error.status_code(),
+                reqwest::StatusCode::UNPROCESSABLE_ENTITY
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_envelopes() {
+        let network = NetworkConfig::default();
+        let owner = PrivateKeySigner::random();
+        let envelope = Envelope::new()
+            .data(Some("hello world".as_bytes().to_vec()))
+            .build()
+            .unwrap();
+        let tx = create_envelope(&BundlerSigner::new(owner.clone()), envelope, &network)
+            .await
+            .unwrap();
+        let valid = TxEnvelopeWrapper::try_from_tx_envelope(tx, None).unwrap();
+        assert_eq!(valid.verify().unwrap(), owner.address());
+
+        // the stored hash no longer matches the signed fields
+        let mut forged = valid.clone();
+        forged.input = "0xdeadbeef".to_string();
+        assert!(matches!(
+            forged.verify(),
+            Err(Error::EnvelopeVerification(_))
+        ));
+
+        let bundle = BundleData::from(vec![valid.clone(), forged.clone()]);
+        let flagged = VerifiedBundleData::verify(bundle, VerificationMode::Flag).unwrap();
+        assert!(!flagged.all_verified());
+        assert_eq!(
+            flagged.envelopes[0].owner,
+            Some(owner.address().to_checksum(Some(network.chain_id)))
+        );
+        assert!(flagged.envelopes[1].owner.is_none());
+        assert!(flagged.envelopes[1].verification_error.is_some());
+
+        let bundle = BundleData::from(vec![valid.clone(), forged]);
+        assert!(matches!(
+            VerifiedBundleData::verify(bundle, VerificationMode::Reject),
+            Err(Error::EnvelopeRuleViolation { index: 1, .. })
+        ));
+
+        // owners are recovered without verifying, as the envelopes-full endpoints do
+        let mut stale = valid;
+        stale.hash = format!("{:#x}", alloy::primitives::B256::ZERO);
+        assert!(stale.verify().is_err());
+        let with_owners = BundleData::to_bundle_with_owners(BundleData::from(vec![stale]))
+            .await
+            .unwrap();
+        assert_eq!(
+            with_owners.envelopes[0].from,
+            owner.address().to_checksum(Some(network.chain_id))
+        );
+    }
+
+    async fn serve_router(state: AppState) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        url
+    }
+
+    // a node mining every accepted tx at block 0x10, but the ones it drops
+    #[derive(Default)]
+    struct MockChain {
+        sent: Vec<TxEnvelope>,
+        dropped: std::collections::HashSet<alloy::primitives::B256>,
+        // the next accepted txs are dropped
+        drop_next: usize,
+        // credited by transfers, 1 tWVM for the accounts not listed
+        balances: std::collections::HashMap<Address, U256>,
+        // calls received per method
+        calls: std::collections::HashMap<String, usize>,
+        // error message every broadcast is rejected with
+        reject_with: Option<String>,
+    }
+
+    type SharedChain = std::sync::Arc<std::sync::Mutex<MockChain>>;
+
+    async fn serve_mock_chain(chain: SharedChain) -> String {
+        let rpc = axum::Router::new()
+            .route(
+                "/",
+                axum::routing::post(
+                    |axum::extract::State(chain): axum::extract::State<SharedChain>,
+                     axum::Json(request): axum::Json<serde_json::Value>| async move {
+                        let param = request["params"][0].as_str().unwrap_or_default().to_string();
+                        let mut chain = chain.lock().unwrap();
+                        let method = request["method"].as_str().unwrap_or_default();
+                        *chain.calls.entry(method.to_string()).or_default() += 1;
+                        if let ("eth_sendRawTransaction", Some(message)) = (method, chain.reject_with.as_ref()) {
+                            return axum::Json(serde_json::json!({
+                                "jsonrpc": "2.0",
+                                "id": request["id"],
+                                "error": { "code": -32000, "message": message }
+                            }));
+                        }
+                        let mined = |hash: &str| {
+                            chain.sent.iter().find(|tx| {
+                                tx.tx_hash().to_string() == hash
+                                    && !chain.dropped.contains(tx.tx_hash())
+                            })
+                        };
+                        let result = match method {
+                            "eth_chainId" => serde_json::json!("0x2518"),
+                            "eth_blockNumber" => serde_json::json!("0x11"),
+                            "eth_getBalance" => {
+                                let balance = param
+                                    .parse::<Address>()
+                                    .ok()
+                                    .and_then(|address| chain.balances.get(&address).copied())
+                                    .unwrap_or(U256::from(1_000_000_000_000_000_000u64));
+                                serde_json::json!(balance)
+                            }
+                            "eth_getTransactionCount" => {
+                                let sent = chain
+                                    .sent
+                                    .iter()
+                                    .filter(|tx| format!("{:#x}", tx.recover_signer().unwrap()) == param.to_lowercase())
+                                    .count();
+                                serde_json::json!(format!("{:#x}", sent))
+                            }
+                            "eth_sendRawTransaction" => {
+                                let raw = hex::decode(param.trim_start_matches("0x")).unwrap();
+                                let tx = TxEnvelope::decode_2718(&mut raw.as_slice()).unwrap();
+                                let hash = *tx.tx_hash();
+                                if chain.drop_next > 0 {
+                                    chain.drop_next -= 1;
+                                    chain.dropped.insert(hash);
+                                }
+                                if let Some(balance) =
+                                    tx.to().and_then(|to| chain.balances.get_mut(&to))
+                                {
+                                    *balance += tx.value();
+                                }
+                                chain.sent.push(tx);
+                                serde_json::json!(hash.to_string())
+                            }
+                            "eth_getTransactionByHash" => match mined(&param) {
+                                Some(tx) => serde_json::to_value(alloy::rpc::types::Transaction {
+                                    inner: tx.clone(),
+                                    block_hash: Some(alloy::primitives::B256::repeat_byte(0xaa)),
+                                    block_number: Some(0x10),
+                                    transaction_index: Some(0),
+                                    effective_gas_price: None,
+                                    from: tx.recover_signer().unwrap(),
+                                })
+                                .unwrap(),
+                                None => serde_json::Value::Null,
+                            },
+                            "eth_getTransactionReceipt" => match mined(&param) {
+                                Some(tx) => serde_json::json!({
+                                    "type": format!("{:#x}", tx.tx_type() as u8),
+                                    "status": "0x1",
+                                    "cumulativeGasUsed": "0x5208",
+                                    "logs": [],
+                                    "logsBloom": format!("0x{}", "00".repeat(256)),
+                                    "transactionHash": param,
+                                    "transactionIndex": "0x0",
+                                    "blockHash": format!("0x{}", "aa".repeat(32)),
+                                    "blockNumber": "0x10",
+                                    "gasUsed": "0x5208",
+                                    "effectiveGasPrice": "0x1",
+                                    "from": tx.recover_signer().unwrap(),
+                                    "to": tx.to(),
+                                    "contractAddress": null
+                                }),
+                                None => serde_json::Value::Null,
+                            },
+                            _ => serde_json::Value::Null,
+                        };
+                        axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+                    },
+                ),
+            )
+            .with_state(chain);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_submit_bundle_reads_back_through_v2() {
+        let chain = SharedChain::default();
+        let network = NetworkConfig::default().rpc_url(serve_mock_chain(chain.clone()).await);
+        let state =
+            AppState::new(network.clone()).payer(BundlerSigner::new(PrivateKeySigner::random()));
+        let url = serve_router(state).await;
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(format!("{}/v2/bundles", url))
+            .json(&serde_json::json!({ "envelopes": [{ "data": "0x68656c6c6f" }] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        let bundle_txid = res.json::<serde_json::Value>().await.unwrap()["bundle_txid"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let sent = chain.lock().unwrap().sent.clone();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to(), Some(network.babe2.parse().unwrap()));
+
+        // the /v2 readers accept what /v2/bundles submitted
+        let res = client
+            .get(format!("{}/v2/envelopes/{}", url, bundle_txid))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        let envelopes = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(envelopes["envelopes"][0]["input"], "0x68656c6c6f");
+    }
+
+    #[tokio::test]
+    async fn test_fee_cap_surfaces_from_propagation() {
+        let chain = SharedChain::default();
+        let network = NetworkConfig::default()
+            .rpc_url(serve_mock_chain(chain.clone()).await)
+            .fees(FeePolicy::new().estimate(false).max_spend_per_bundle(1));
+        let payer = BundlerSigner::new(PrivateKeySigner::random());
+
+        let envelope = Envelope::new()
+            .data(Some(b"hello".to_vec()))
+            .target(None)
+            .build()
+            .unwrap();
+        let result = Bundle::new()
+            .signer(payer.clone())
+            .network(network.clone())
+            .envelopes(vec![envelope])
+            .build()
+            .unwrap()
+            .propagate()
+            .await;
+        assert!(matches!(result, Err(Error::FeeCapExceeded { .. })));
+
+        let url = serve_router(AppState::new(network).payer(payer)).await;
+        let res = reqwest::Client::new()
+            .post(format!("{}/v2/bundles", url))
+            .json(&serde_json::json!({ "envelopes": [{ "data": "0x68656c6c6f" }] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 402);
+        assert!(chain.lock().unwrap().sent.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_submit_bundle_rejections() {
+        let serve =
+            |state: AppState| async move { format!("{}/v2/bundles", serve_router(state).await) };
+        let client = reqwest::Client::new();
+        let envelope = serde_json::json!({ "envelopes": [{ "data": "0x68656c6c6f" }] });
+
+        // submission is disabled without a payer key
+        let url = serve(AppState::new(NetworkConfig::default())).await;
+        let res = client.post(&url).json(&envelope).send().await.unwrap();
+        assert_eq!(res.status(), 503);
+        // any other missing key is a server fault
+        assert_eq!(
+            Error::PrivateKeyNeeded.status_code(),
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let state = AppState::new(NetworkConfig::default())
+            .payer(BundlerSigner::new(PrivateKeySigner::random()));
+        let url = serve(state).await;
+
+        let res = client
+            .post(&url)
+            .json(&serde_json::json!({}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 400);
+
+        let tags = vec![Tag::new("a".repeat(2048), "b".to_string())];
+        let res = client
+            .post(&url)
+            .json(&serde_json::json!({ "envelopes": [{ "data": "0x68656c6c6f", "tags": tags }] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 422);
+
+        let res = client
+            .post(&url)
+            .json(&serde_json::json!({ "envelopes": [{ "data": "00".repeat(SUBMISSION_BODY_LIMIT) }] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 413);
+    }
+
+    #[tokio::test]
+    async fn test_upload_large_bundle_tops_up_chunkers() {
+        let chain = SharedChain::default();
+        let chunkers: Vec<BundlerSigner> = (0..2)
+            .map(|_| BundlerSigner::new(PrivateKeySigner::random()))
+            .collect();
+        for chunker in &chunkers {
+            chain
+                .lock()
+                .unwrap()
+                .balances
+                .insert(chunker.address(), U256::ZERO);
+        }
+        let network = NetworkConfig::default().rpc_url(serve_mock_chain(chain.clone()).await);
+        let payer = BundlerSigner::new(PrivateKeySigner::random());
+        let state = AppState::new(network)
+            .payer(payer.clone())
+            .chunkers(chunkers.clone());
+        let url = serve_router(state).await;
+
+        let res = reqwest::Client::new()
+            .post(format!("{}/v2/large-bundles", url))
+            .body("~UwU~")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+
+        // the payer funded both chunkers before they sent the chunk
+        let chain = chain.lock().unwrap();
+        for chunker in &chunkers {
+            assert!(chain.balances[&chunker.address()] > U256::ZERO);
+            assert!(chain.sent.iter().any(|tx| {
+                tx.to() == Some(chunker.address())
+                    && tx.recover_signer().unwrap() == payer.address()
+            }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_large_bundle_rejections() {
+        let client = reqwest::Client::new();
+
+        let url = serve_router(AppState::new(NetworkConfig::default())).await;
+        let res = client
+            .post(format!("{}/v2/large-bundles", url))
+            .body("~UwU~")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 503);
+
+        let state = AppState::new(NetworkConfig::default())
+            .payer(BundlerSigner::new(PrivateKeySigner::random()));
+        let url = serve_router(state).await;
+        let res = client
+            .post(format!("{}/v2/large-bundles", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 400);
+    }
+
+    #[test]
+    fn test_range_header_parsing() {
+        let range = |start, end| ByteRange { start, end };
+
+        assert_eq!(
+            parse_range_header("bytes=0-99", 1000),
+            RangeRequest::Partial(vec![range(0, 99)])
+        );
+        assert_eq!(
+            parse_range_header("bytes=900-", 1000),
+            RangeRequest::Partial(vec![range(900, 999)])
+        );
+        assert_eq!(
+            parse_range_header("bytes=-100, 10-2000", 1000),
+            RangeRequest::Partial(vec![range(900, 999), range(10, 999)])
+        );
+        assert_eq!(
+            parse_range_header("bytes=1000-", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(
+            parse_range_header("bytes=-0", 1000),
+            RangeRequest::Unsatisfiable
+        );
+        assert_eq!(parse_range_header("bytes=50-10", 1000), RangeRequest::Full);
+        assert_eq!(parse_range_header("items=0-10", 1000), RangeRequest::Full);
+
+        // only the chunks intersecting the range are fetched, first and last sliced
+        assert_eq!(
+            range_segments(&range(150, 349), 100),
+            vec![
+                Segment::Chunk {
+                    index: 1,
+                    start: 50,
+                    end: 100
+                },
+                Segment::Chunk {
+                    index: 2,
+                    start: 0,
+                    end: 100
+                },
+                Segment::Chunk {
+                    index: 3,
+                    start: 0,
+                    end: 50
+                },
+            ]
+        );
+        assert_eq!(range(150, 349).content_range(1000), "bytes 150-349/1000");
+    }
+
+    #[test]
+    fn test_large_bundle_manifest_formats() {
+        let chunks = [vec![1u8; 4], vec![2u8; 4], vec![3u8; 2]];
+        let entries: Vec<ChunkEntry> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| ChunkEntry::new(format!("{:064x}", i), chunk))
+            .collect();
+        let data = chunks.concat();
+        let manifest =
+            LargeBundleManifest::new(entries, 4, alloy::primitives::keccak256(&data).to_string())
+                .content_type(Some("text/plain".to_string()))
+                .filename(Some("uwu.txt".to_string()));
+
+        assert_eq!(manifest.total_size, 10);
+        assert_eq!(manifest.chunk_offset(2), 8);
+        assert_eq!(
+            manifest.chunks[1].hash,
+            alloy::primitives::keccak256(&chunks[1]).to_string()
+        );
+
+        // v2 manifest
+        let (txids, parsed) = parse_manifest(&serde_json::to_vec(&manifest).unwrap()).unwrap();
+        assert_eq!(parsed, Some(manifest.clone()));
+        assert_eq!(txids, manifest.txids());
+        assert!(!txids[0].starts_with("0x"));
+        let mut prefixed = manifest.clone();
+        prefixed.chunks[0].txid = format!("0x{}", txids[0]);
+        let (prefixed, _) = parse_manifest(&serde_json::to_vec(&prefixed).unwrap()).unwrap();
+        assert_eq!(prefixed, txids);
+
+        // v1 array of unprefixed txids, prefixed ones are read the same
+        let (v1_txids, parsed) = parse_manifest(&serde_json::to_vec(&txids).unwrap()).unwrap();
+        assert_eq!(parsed, None);
+        assert_eq!(v1_txids, txids);
+        let prefixed: Vec<String> = txids.iter().map(|t| format!("0x{}", t)).collect();
+        let (prefixed, _) = parse_manifest(&serde_json::to_vec(&prefixed).unwrap()).unwrap();
+        assert_eq!(prefixed, txids);
+
+        assert!(parse_manifest(b"{\"version\": 3}").is_err());
+    }
+
+    #[test]
+    fn test_verify_chunk_integrity() {
+        let data = vec![7u8; 16];
+        let chunk = |index: &str, data: &[u8]| TxEnvelopeWrapper {
+            input: format!("0x{}", hex::encode(data)),
+            tags: Some(vec![Tag::new("chunk_index".to_string(), index.to_string())]),
+            ..Default::default()
+        };
+        let manifest = LargeBundleManifest::new(
+            vec![ChunkEntry::new("0x01".to_string(), &data)],
+            16,
+            alloy::primitives::keccak256(&data).to_string(),
+        );
+
+        assert_eq!(
+            LargeBundle::verify_chunk(0, &chunk("0", &data), Some(&manifest)).unwrap(),
+            data
+        );
+        // v1 Large Bundles only have the chunk_index tag to check
+        assert!(LargeBundle::verify_chunk(0, &chunk("0", &[1u8; 3]), None).is_ok());
+
+        let mut corrupted = data.clone();
+        corrupted[3] = 0;
+        for envelope in [
+            chunk("0", &corrupted),
+            chunk("0", &data[..8]),
+            chunk("1", &data),
+            TxEnvelopeWrapper::default(),
+        ] {
+            assert!(matches!(
+                LargeBundle::verify_chunk(0, &envelope, Some(&manifest)),
+                Err(Error::ChunkIntegrity { index: 0, .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_journal_resume() {
+        let path = std::env::temp_dir()
+            .join(format!("bundler-{:016x}", rand::random::<u64>()))
+            .join("upload.journal");
+        let chunk = vec![1u8; 8];
+        let hash = alloy::primitives::keccak256(&chunk).to_string();
+
+        let mut journal = UploadJournal::open(&path).unwrap();
+        journal
+            .record_chunk(0, ChunkEntry::new("0xaa".to_string(), &chunk))
+            .unwrap();
+        drop(journal);
+
+        // a crash in the middle of a write leaves a truncated line
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        std::io::Write::write_all(&mut file, b"{\"index\": 1, \"txid\"").unwrap();
+
+        let mut journal = UploadJournal::open(&path).unwrap();
+        assert_eq!(journal.chunks_count(), 1);
+        assert_eq!(journal.confirmed(0, &hash).unwrap().txid, "aa");
+        // recorded for other data
+        assert!(journal.confirmed(0, "0x00").is_none());
+        assert!(journal.confirmed(1, &hash).is_none());
+        assert!(journal.finalized().is_none());
+
+        journal.record_finalized("0xbb".to_string()).unwrap();
+        drop(journal);
+
+        // an already finalized upload is not sent again
+        let large_bundle_txid = LargeBundle::new()
+            .data(chunk)
+            .private_key(
+                "6f142508b4eea641e33cb2a0161221105086a84584c74245ca463a49effea30b".to_string(),
+            )
+            .chunk()
+            .build()
+            .unwrap()
+            .resume(&path)
+            .await
+            .unwrap();
+        assert_eq!(large_bundle_txid, "0xbb");
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_chunk_inclusion() {
+        let mined = format!("0x{}", "11".repeat(32));
+        let reverted = format!("0x{}", "22".repeat(32));
+        let pending = format!("0x{}", "33".repeat(32));
+
+        // answers receipts of the mined and reverted txs at block 0x10, head at 0x11
+        let rpc = axum::Router::new().route(
+            "/",
+            axum::routing::post(|axum::Json(request): axum::Json<serde_json::Value>| async move {
+                let params = request["params"].clone();
+                let txid = params[0].as_str().unwrap_or_default().to_string();
+                let result = match request["method"].as_str().unwrap_or_default() {
+                    "eth_blockNumber" => serde_json::json!("0x11"),
+                    "eth_getTransactionReceipt" if txid.starts_with("0x1111") || txid.starts_with("0x2222") => {
+                        serde_json::json!({
+                            "type": "0x2",
+                            "status": if txid.starts_with("0x1111") { "0x1" } else { "0x0" },
+                            "cumulativeGasUsed": "0x5208",
+                            "logs": [],
+                            "logsBloom": format!("0x{}", "00".repeat(256)),
+                            "transactionHash": txid,
+                            "transactionIndex": "0x0",
+                            "blockHash": format!("0x{}", "aa".repeat(32)),
+                            "blockNumber": "0x10",
+                            "gasUsed": "0x5208",
+                            "effectiveGasPrice": "0x1",
+                            "from": format!("0x{}", "01".repeat(20)),
+                            "to": ADDRESS_BABE1,
+                            "contractAddress": null
+                        })
+                    }
+                    _ => serde_json::Value::Null,
+                };
+                axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+        let provider = create_evm_http_client(&url).await.unwrap();
+
+        let policy = InclusionPolicy::new()
+            .confirmations(2)
+            .timeout(Duration::from_millis(50))
+            .poll_interval(Duration::from_millis(10));
+        let wait = |txid: String, policy: InclusionPolicy| {
+            let provider = provider.clone();
+            async move { wait_for_inclusion(&provider, &txid, &policy).await.unwrap() }
+        };
+
+        assert_eq!(
+            wait(mined.clone(), policy.clone()).await,
+            Inclusion::Confirmed
+        );
+        // the txid is accepted without the 0x prefix as stored in the receipts
+        assert_eq!(
+            wait(mined.trim_start_matches("0x").to_string(), policy.clone()).await,
+            Inclusion::Confirmed
+        );
+        // mined but not deep enough before the timeout
+        assert_eq!(
+            wait(mined, policy.clone().confirmations(3)).await,
+            Inclusion::Pending
+        );
+        assert_eq!(wait(reverted, policy.clone()).await, Inclusion::Failed);
+        assert_eq!(wait(pending, policy).await, Inclusion::Dropped);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_chunks_resubmits_streamed_chunks() {
+        let chain = SharedChain::default();
+        let network = NetworkConfig::default().rpc_url(serve_mock_chain(chain.clone()).await);
+        // a tx is found dropped once the timeout elapses
+        let policy = InclusionPolicy::new()
+            .timeout(Duration::from_millis(200))
+            .poll_interval(Duration::from_millis(10));
+        let private_key = format!("{:#x}", PrivateKeySigner::random().to_bytes());
+        let data = generate_random_calldata(1_000);
+
+        // the chunk tx is dropped, the chunk is read back from the file
+        let path = std::env::temp_dir().join(format!("bundler-resubmit-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        chain.lock().unwrap().drop_next = 1;
+        let large_bundle = LargeBundle::from_path(&path)
+            .private_key(private_key.clone())
+            .network(network.clone())
+            .inclusion(policy.clone())
+            .build()
+            .unwrap()
+            .propagate_chunks()
+            .await
+            .unwrap()
+            .confirm_chunks()
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let sent = chain.lock().unwrap().sent.clone();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].input(), sent[1].input());
+        let resubmitted = sent[1].tx_hash().to_string();
+        let resubmitted = resubmitted.trim_start_matches("0x");
+        assert_eq!(large_bundle.chunks_receipts.unwrap()[0], resubmitted);
+        assert_eq!(large_bundle.manifest.unwrap().chunks[0].txid, resubmitted);
+
+        // a reader can't be read again
+        chain.lock().unwrap().drop_next = 1;
+        let result = LargeBundle::from_reader(std::io::Cursor::new(data.into_bytes()))
+            .private_key(private_key)
+            .network(network)
+            .inclusion(policy)
+            .build()
+            .unwrap()
+            .propagate_chunks()
+            .await
+            .unwrap()
+            .confirm_chunks()
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::ChunkNotIncluded { index: 0, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resubmitted_chunks_keep_their_signer() {
+        let chain = SharedChain::default();
+        let network = NetworkConfig::default().rpc_url(serve_mock_chain(chain.clone()).await);
+        let policy = InclusionPolicy::new()
+            .timeout(Duration::from_millis(200))
+            .poll_interval(Duration::from_millis(10));
+        let (signer, payer) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+
+        // the chunk tx is dropped, the payer sends it again
+        chain.lock().unwrap().drop_next = 1;
+        LargeBundle::new()
+            .data(generate_random_calldata(1_000).into_bytes())
+            .signer(BundlerSigner::new(signer.clone()))
+            .payer(BundlerSigner::new(payer.clone()))
+            .network(network)
+            .inclusion(policy)
+            .chunk()
+            .build()
+            .unwrap()
+            .propagate_chunks()
+            .await
+            .unwrap()
+            .confirm_chunks()
+            .await
+            .unwrap();
+
+        let sent = chain.lock().unwrap().sent.clone();
+        assert_eq!(sent.len(), 2);
+        for tx in sent {
+            assert_eq!(tx.recover_signer().unwrap(), payer.address());
+            let bundle = retrieve_bundle_data(hex::encode(tx.input())).await.unwrap();
+            assert_eq!(bundle.envelopes[0].verify().unwrap(), signer.address());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_chunks_match_in_memory_chunks() {
+        let chain = SharedChain::default();
+        let network = NetworkConfig::default().rpc_url(serve_mock_chain(chain.clone()).await);
+        let (signer, payer) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+        let data = generate_random_calldata(1_000).into_bytes();
+        let path = std::env::temp_dir().join(format!("bundler-streamed-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+
+        for large_bundle in [
+            LargeBundle::new().data(data.clone()).chunk(),
+            LargeBundle::from_path(&path),
+        ] {
+            large_bundle
+                .signer(BundlerSigner::new(signer.clone()))
+                .payer(BundlerSigner::new(payer.clone()))
+                .network(network.clone())
+                .build()
+                .unwrap()
+                .propagate_chunks()
+                .await
+                .unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        // the same envelope, signed by the signer and paid by the payer
+        let sent = chain.lock().unwrap().sent.clone();
+        assert_eq!(sent.len(), 2);
+        let mut envelopes = Vec::new();
+        for tx in sent {
+            assert_eq!(tx.recover_signer().unwrap(), payer.address());
+            let bundle = retrieve_bundle_data(hex::encode(tx.input())).await.unwrap();
+            assert_eq!(bundle.envelopes[0].verify().unwrap(), signer.address());
+            envelopes.push(bundle.envelopes[0].hash.clone());
+        }
+        assert_eq!(envelopes[0], envelopes[1]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_confirmed_chunks() {
+        let chain = SharedChain::default();
+        let network = NetworkConfig::default().rpc_url(serve_mock_chain(chain.clone()).await);
+        let private_key = format!("{:#x}", PrivateKeySigner::random().to_bytes());
+        let data = generate_random_calldata(1_000);
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("bundler-resume-{}", std::process::id()));
+        let journal = dir.join(format!("bundler-resume-{}.journal", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let _ = std::fs::remove_file(&journal);
+        let large_bundle = || {
+            LargeBundle::from_path(&path)
+                .private_key(private_key.clone())
+                .network(network.clone())
+                .build()
+                .unwrap()
+        };
+
+        // the upload is interrupted before its finalization
+        large_bundle()
+            .journal(journal.to_string_lossy().to_string())
+            .propagate_chunks()
+            .await
+            .unwrap();
+        let chunk_txid = chain.lock().unwrap().sent[0].tx_hash().to_string();
+        let confirmed = UploadJournal::open(&journal).unwrap();
+        assert_eq!(confirmed.chunks_count(), 1);
+        let hash = alloy::primitives::keccak256(data.as_bytes()).to_string();
+        assert_eq!(
+            confirmed.confirmed(0, &hash).unwrap().txid,
+            chunk_txid.trim_start_matches("0x")
+        );
+
+        // resuming only sends the manifest
+        let large_bundle_txid = large_bundle().resume(&journal).await.unwrap();
+        let sent = chain.lock().unwrap().sent.clone();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].tx_hash().to_string(), large_bundle_txid);
+        assert_ne!(sent[0].input(), sent[1].input());
+
+        // a finalized upload is not sent again
+        assert_eq!(
+            large_bundle().resume(&journal).await.unwrap(),
+            large_bundle_txid
+        );
+        assert_eq!(chain.lock().unwrap().sent.len(), 2);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&journal).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_large_bundle_sources() {
+        use futures::TryStreamExt;
+
+        let data = generate_random_calldata(200_000);
+        let collect = |source: LargeBundleSource| async move {
+            let frames: Vec<bytes::Bytes> = source.open().await?.try_collect().await.unwrap();
+            Ok::<_, Error>(frames.concat())
+        };
+
+        // a reader is read once, in bounded frames
+        let source = LargeBundleSource::reader(std::io::Cursor::new(data.clone().into_bytes()));
+        assert_eq!(collect(source.clone()).await.unwrap(), data.as_bytes());
+        assert!(matches!(collect(source).await, Err(Error::Other(_))));
+
+        let path = std::env::temp_dir().join(format!("bundler-source-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let large_bundle = LargeBundle::from_path(&path);
+        let source = large_bundle.source.clone().unwrap();
+        assert_eq!(collect(source.clone()).await.unwrap(), data.as_bytes());
+        // a path can be read again, to resume an upload
+        assert_eq!(collect(source).await.unwrap(), data.as_bytes());
+        // its size is known upfront, so its chunkers can be topped up
+        assert_eq!(large_bundle.size().await, Some(200_000));
+        assert_eq!(
+            LargeBundle::from_reader(tokio::io::empty()).size().await,
+            None
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        // streamed data is neither loaded nor chunked when building
+        let large_bundle = large_bundle
+            .private_key(
+                "6f142508b4eea641e33cb2a0161221105086a84584c74245ca463a49effea30b".to_string(),
+            )
+            .build()
+            .unwrap();
+        assert!(large_bundle.data.is_none() && large_bundle.chunks.is_none());
+        assert!(matches!(
+            LargeBundle::from_reader(tokio::io::empty()).build(),
+            Err(Error::PrivateKeyNeeded)
+        ));
+        assert!(matches!(
+            LargeBundle::new().build(),
+            Err(Error::EnvelopesNeeded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_chunk_retries_transport_errors_only() {
+        let chain = SharedChain::default();
+        let network = NetworkConfig::default().rpc_url(serve_mock_chain(chain.clone()).await);
+        let envelope = Envelope::new()
+            .data(Some(b"hello".to_vec()))
+            .target(None)
+            .build()
+            .unwrap();
+        // a babe1 bundle is not a chunk
+        let txid = Bundle::new()
+            .signer(BundlerSigner::new(PrivateKeySigner::random()))
+            .network(network.clone())
+            .envelopes(vec![envelope])
+            .build()
+            .unwrap()
+            .propagate()
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            LargeBundle::retrieve_chunk(0, &txid, None, &network).await,
+            Err(Error::UnverifiedAddress)
+        ));
+        assert_eq!(chain.lock().unwrap().calls["eth_getTransactionByHash"], 1);
+
+        // nothing listens there, the transport error is retried
+        let unreachable = NetworkConfig::custom("http://127.0.0.1:1".to_string(), 9496);
+        assert!(matches!(
+            LargeBundle::retrieve_chunk(0, &txid, None, &unreachable).await,
+            Err(Error::RpcTransportError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_download_to_path_resumes() {
+        let chunks = [vec![1u8; 4], vec![2u8; 4], vec![3u8; 2]];
+        let entries: Vec<ChunkEntry> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| ChunkEntry::new(format!("0x{:064x}", i), chunk))
+            .collect();
+        let data = chunks.concat();
+        let manifest =
+            LargeBundleManifest::new(entries, 4, alloy::primitives::keccak256(&data).to_string());
+        let large_bundle = LargeBundle {
+            chunks_receipts: Some(manifest.txids()),
+            manifest: Some(manifest),
+            // nothing listens there, any chunk retrieval fails
+            network: NetworkConfig::custom("http://127.0.0.1:1".to_string(), 9496),
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!("bundler-download-{}", std::process::id()));
+
+        // a complete previous download, with trailing bytes, is only truncated
+        std::fs::write(&path, [data.clone(), vec![9u8; 3]].concat()).unwrap();
+        let mut progress = Vec::new();
+        let size = large_bundle
+            .download_to_path(&path, 2, |p| progress.push(p))
+            .await
+            .unwrap();
+        assert_eq!(size, 10);
+        assert!(progress.is_empty());
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+
+        // chunks matching their entries but not the whole data hash
+        let mismatching = LargeBundle {
+            manifest: large_bundle
+                .manifest
+                .clone()
+                .map(|manifest| LargeBundleManifest {
+                    hash: alloy::primitives::keccak256(b"other data").to_string(),
+                    ..manifest
+                }),
+            ..large_bundle.clone()
+        };
+        std::fs::write(&path, &data).unwrap();
+        assert!(matches!(
+            mismatching.download_to_path(&path, 2, |_| {}).await,
+            Err(Error::LargeBundleHashMismatch { .. })
+        ));
+
+        // a corrupted second chunk is dropped, with everything after it
+        std::fs::write(&path, [chunks[0].clone(), vec![0u8; 6]].concat()).unwrap();
+        assert!(large_bundle
+            .download_to_path(&path, 2, |_| {})
+            .await
+            .is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), chunks[0]);
+
+        // without a manifest nothing written can be checked, so it is dropped
+        let v1 = LargeBundle {
+            manifest: None,
+            ..large_bundle.clone()
+        };
+        std::fs::write(&path, &data).unwrap();
+        assert!(v1.download_to_path(&path, 2, |_| {}).await.is_err());
+        assert!(std::fs::read(&path).unwrap().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_hierarchical_manifest() {
+        let chunks: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 4]).collect();
+        let entries: Vec<ChunkEntry> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| ChunkEntry::new(format!("{:064x}", i), chunk))
+            .collect();
+        let data = chunks.concat();
+        let manifest =
+            LargeBundleManifest::new(entries, 4, alloy::primitives::keccak256(&data).to_string());
+
+        // parts of 2 chunks, as stored by finalize
+        let parts_data: Vec<Vec<u8>> = manifest
+            .chunks
+            .chunks(2)
+            .map(|part| serde_json::to_vec(part).unwrap())
+            .collect();
+        let parts: Vec<ManifestPart> = parts_data
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                let count = if i == 2 { 1 } else { 2 };
+                ManifestPart::new(format!("{:064x}", 100 + i), i * 2, count, part)
+            })
+            .collect();
+        let root = manifest.clone().into_hierarchical(parts);
+        assert!(root.chunks.is_empty());
+
+        let (txids, parsed) = parse_manifest(&serde_json::to_vec(&root).unwrap()).unwrap();
+        let parsed = parsed.unwrap();
+        assert!(parsed.is_hierarchical() && txids.is_empty());
+        assert!(!parsed.parts[0].txid.starts_with("0x"));
+
+        let parts_chunks: Vec<Vec<ChunkEntry>> = parsed
+            .parts
+            .iter()
+            .zip(&parts_data)
+            .map(|(part, data)| part.parse_chunks(data).unwrap())
+            .collect();
+        let restored = parsed
+            .clone()
+            .with_parts_chunks(parts_chunks.clone())
+            .unwrap();
+        assert_eq!(restored.txids(), manifest.txids());
+        assert_eq!(restored.chunk_offset(4), 16);
+
+        // parts are checked against their hash and position
+        assert!(matches!(
+            parsed.parts[0].parse_chunks(&parts_data[1]),
+            Err(Error::ManifestIntegrity(_))
+        ));
+        let mut swapped = parts_chunks;
+        swapped.swap(0, 1);
+        let mut reordered = parsed.clone();
+        reordered.parts.swap(0, 1);
+        assert!(matches!(
+            reordered.with_parts_chunks(swapped),
+            Err(Error::ManifestIntegrity(_))
+        ));
+        // unknown manifest versions are rejected
+        let future = LargeBundleManifest {
+            version: 9,
+            ..manifest.clone()
+        };
+        assert!(matches!(
+            parse_manifest(&serde_json::to_vec(&future).unwrap()),
+            Err(Error::ManifestIntegrity(_))
+        ));
+        assert_eq!(
+            Error::ManifestIntegrity(String::new()).status_code(),
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY
+        );
+        // a stored v2 manifest is unchanged
+        assert!(!serde_json::to_string(&manifest).unwrap().contains("parts"));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_manager() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        // the node's pending transactions count
+        let pending = Arc::new(AtomicU64::new(5));
+        let rpc = axum::Router::new()
+            .route(
+                "/",
+                axum::routing::post(
+                    |axum::extract::State(pending): axum::extract::State<Arc<AtomicU64>>,
+                     axum::Json(request): axum::Json<serde_json::Value>| async move {
+                        assert_eq!(request["method"], "eth_getTransactionCount");
+                        assert_eq!(request["params"][1], "pending");
+                        let count = format!("{:#x}", pending.load(Ordering::SeqCst));
+                        axum::Json(serde_json::json!({
+                            "jsonrpc": "2.0", "id": request["id"], "result": count
+                        }))
+                    },
+                ),
+            )
+            .with_state(pending.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+        let provider = create_evm_http_client(&url).await.unwrap();
+
+        let nonces = NonceManager::new();
+        let address = PrivateKeySigner::random().address();
+        let next = || nonces.next(&provider, 9496, address);
+
+        // concurrent callers get distinct sequential nonces
+        let mut handed = futures::future::try_join_all([next(), next(), next()])
+            .await
+            .unwrap();
+        handed.sort_by_key(NonceGuard::nonce);
+        let handed_nonces: Vec<u64> = handed.iter().map(NonceGuard::nonce).collect();
+        assert_eq!(handed_nonces, vec![5, 6, 7]);
+
+        // in flight nonces are not read again from the node
+        pending.store(40, Ordering::SeqCst);
+        handed.remove(1).release();
+        let six = next().await.unwrap();
+        assert_eq!(six.nonce(), 6);
+        let eight = next().await.unwrap();
+        assert_eq!(eight.nonce(), 8);
+
+        // once idle, the node's count is read again, dropping any gap
+        for guard in handed.into_iter().chain([six, eight]) {
+            guard.confirm();
+        }
+        pending.store(7, Ordering::SeqCst);
+        let seven = next().await.unwrap();
+        assert_eq!(seven.nonce(), 7);
+        let other = PrivateKeySigner::random().address();
+        assert_eq!(
+            nonces.next(&provider, 9496, other).await.unwrap().nonce(),
+            7
+        );
+
+        // a nonce used elsewhere catches up with the node
+        pending.store(12, Ordering::SeqCst);
+        let eight = next().await.unwrap();
+        assert_eq!(eight.nonce(), 8);
+        seven.resync(&provider).await.unwrap();
+        let twelve = next().await.unwrap();
+        assert_eq!(twelve.nonce(), 12);
+
+        // a guard dropped before being confirmed hands its nonce out again
+        drop(twelve);
+        assert_eq!(next().await.unwrap().nonce(), 12);
+        drop(eight);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_broadcast_releases_nonce() {
+        // the node never answers the broadcast
+        let rpc = axum::Router::new().route(
+            "/",
+            axum::routing::post(|axum::Json(request): axum::Json<serde_json::Value>| async move {
+                let result = match request["method"].as_str().unwrap_or_default() {
+                    "eth_getTransactionCount" => serde_json::json!("0x3"),
+                    "eth_sendRawTransaction" => std::future::pending().await,
+                    _ => serde_json::Value::Null,
+                };
+                axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+        let provider = create_evm_http_client(&url).await.unwrap();
+        let network = NetworkConfig::default().rpc_url(url);
+        let payer = BundlerSigner::new(PrivateKeySigner::random());
+
+        let broadcast =
+            broadcast_bundle(vec![1, 2, 3], &provider, &payer, &network.babe1, &network);
+        assert!(tokio::time::timeout(Duration::from_millis(300), broadcast)
+            .await
+            .is_err());
+
+        // the cancelled broadcast's nonce is not left in flight
+        let nonce = NonceManager::global()
+            .next(&provider, network.chain_id, payer.address())
+            .await
+            .unwrap();
+        assert_eq!(nonce.nonce(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_caps_nonce_resyncs() {
+        let chain = SharedChain::default();
+        let url = serve_mock_chain(chain.clone()).await;
+        let provider = create_evm_http_client(&url).await.unwrap();
+        let network = NetworkConfig::default().rpc_url(url);
+        let payer = BundlerSigner::new(PrivateKeySigner::random());
+
+        // the node keeps reporting the nonce as used
+        chain.lock().unwrap().reject_with = Some("nonce too low".to_string());
+        let result =
+            broadcast_bundle(vec![1, 2, 3], &provider, &payer, &network.babe1, &network).await;
+        assert!(matches!(
+            result,
+            Err(Error::NonceResyncsExhausted { resyncs: 5, .. })
+        ));
+        assert_eq!(chain.lock().unwrap().calls["eth_sendRawTransaction"], 6);
+
+        // the nonce is released once the broadcast gives up
+        chain.lock().unwrap().reject_with = None;
+        let tx = broadcast_bundle(vec![1, 2, 3], &provider, &payer, &network.babe1, &network)
+            .await
+            .unwrap();
+        let sent = chain.lock().unwrap().sent.clone();
+        assert_eq!(sent[0].tx_hash(), tx.tx_hash());
+        assert_eq!(sent[0].nonce(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_chunkers_balances() {
+        let funded = PrivateKeySigner::random();
+        let empty = PrivateKeySigner::random();
+        let funded_address = format!("{:#x}", funded.address());
+
+        let rpc = axum::Router::new().route(
+            "/",
+            axum::routing::post(
+                move |axum::Json(request): axum::Json<serde_json::Value>| async move {
+                    let result = match request["method"].as_str() {
+                        Some("eth_getBalance") if request["params"][0] == funded_address => {
+                            "0xde0b6b3a7640000"
+                        }
+                        Some("eth_getBalance") => "0x0",
+                        Some("eth_getTransactionCount") => "0x3",
+                        method => panic!("unexpected {:?}", method),
+                    };
+                    axum::Json(serde_json::json!({
+                        "jsonrpc": "2.0", "id": request["id"], "result": result
+                    }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+
+        let mut account = SuperAccount::new().network(NetworkConfig::default().rpc_url(url));
+        assert!(matches!(
+            account.balances().await,
+            Err(Error::ChunkersNeeded)
+        ));
+        account.chunkers = Some(vec![funded.clone(), empty.clone()]);
+
+        let balances = account.balances().await.unwrap();
+        assert_eq!(balances[0].address, funded.address());
+        assert_eq!(
+            balances[0].balance,
+            U256::from(1_000_000_000_000_000_000u64)
+        );
+        assert_eq!(balances[1].address, empty.address());
+        assert_eq!(balances[1].balance, U256::ZERO);
+        assert!(balances.iter().all(|chunker| chunker.pending_nonce == 3));
+
+        // topping up needs a funder, and sends nothing when all are funded
+        assert!(matches!(
+            account.top_up(U256::ZERO, U256::from(1)).await,
+            Err(Error::PrivateKeyNeeded)
+        ));
+        let account = account.funder(format!("{:#x}", PrivateKeySigner::random().to_bytes()));
+        assert!(account
+            .top_up(U256::ZERO, U256::from(u64::MAX))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_chunkers() {
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        type Balances = Arc<Mutex<HashMap<String, String>>>;
+        type Sent = Arc<Mutex<Vec<TxEnvelope>>>;
+        let balances: Balances = Default::default();
+        let sent: Sent = Default::default();
+
+        // answers balances, records the raw txs and mines them right away
+        let rpc = axum::Router::new()
+            .route(
+                "/",
+                axum::routing::post(
+                    |axum::extract::State((balances, sent)): axum::extract::State<(Balances, Sent)>,
+                     axum::Json(request): axum::Json<serde_json::Value>| async move {
+                        let param = request["params"][0].as_str().unwrap_or_default().to_string();
+                        let result = match request["method"].as_str().unwrap_or_default() {
+                            "eth_getBalance" => serde_json::json!(balances.lock().unwrap()[&param]),
+                            "eth_getTransactionCount" => serde_json::json!("0x0"),
+                            "eth_sendRawTransaction" => {
+                                let raw = hex::decode(param.trim_start_matches("0x")).unwrap();
+                                let tx = TxEnvelope::decode_2718(&mut raw.as_slice()).unwrap();
+                                let hash = tx.tx_hash().to_string();
+                                sent.lock().unwrap().push(tx);
+                                serde_json::json!(hash)
+                            }
+                            "eth_getTransactionReceipt" => serde_json::json!({
+                                "type": "0x0",
+                                "status": "0x1",
+                                "cumulativeGasUsed": "0x5208",
+                                "logs": [],
+                                "logsBloom": format!("0x{}", "00".repeat(256)),
+                                "transactionHash": param,
+                                "transactionIndex": "0x0",
+                                "blockHash": format!("0x{}", "aa".repeat(32)),
+                                "blockNumber": "0x10",
+                                "gasUsed": "0x5208",
+                                "effectiveGasPrice": "0x1",
+                                "from": format!("0x{}", "01".repeat(20)),
+                                "to": format!("0x{}", "02".repeat(20)),
+                                "contractAddress": null
+                            }),
+                            _ => serde_json::Value::Null,
+                        };
+                        axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+                    },
+                ),
+            )
+            .with_state((balances.clone(), sent.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+
+        let network = NetworkConfig::default().rpc_url(url);
+        let keystore_path =
+            std::env::temp_dir().join(format!("bundler-sweep-{}", std::process::id()));
+        let account = SuperAccount::new()
+            .keystore_path(keystore_path.to_string_lossy().to_string())
+            .pwd("test".to_string())
+            .network(network.clone())
+            .create_chunkers(3)
+            .await
+            .unwrap()
+            .load_chunkers(None)
+            .await
+            .unwrap();
+        let chunkers = account.chunkers.clone().unwrap();
+        assert_eq!(chunkers.len(), 3);
+
+        // a funded chunker, one holding exactly the transfer fee and an empty one
+        let fee = 21_000 * network.gas.transfer_gas_price;
+        for (chunker, balance) in chunkers.iter().zip([fee * 1000, fee, 0]) {
+            balances.lock().unwrap().insert(
+                format!("{:#x}", chunker.address()),
+                format!("{:#x}", balance),
+            );
+        }
+
+        let to = PrivateKeySigner::random().address();
+        let swept = account
+            .sweep(Some(to), KeystoreRetention::Archive)
+            .await
+            .unwrap();
+        assert_eq!(swept.len(), 1);
+
+        let sent = sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to(), Some(to));
+        assert_eq!(sent[0].gas_limit(), 21_000);
+        assert_eq!(sent[0].value(), U256::from(fee * 999));
+        assert_eq!(sent[0].recover_signer().unwrap(), chunkers[0].address());
+
+        // the drained keystores are archived and no longer loaded
+        assert_eq!(
+            std::fs::read_dir(keystore_path.join("archive"))
+                .unwrap()
+                .count(),
+            3
+        );
+        let reloaded = account.clone().load_chunkers(None).await.unwrap();
+        assert!(reloaded.chunkers.unwrap().is_empty());
+        std::fs::remove_dir_all(&keystore_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keystore_index() {
+        let keystore_path =
+            std::env::temp_dir().join(format!("bundler-keystore-index-{}", std::process::id()));
+        let account = SuperAccount::new()
+            .keystore_path(keystore_path.to_string_lossy().to_string())
+            .pwd("test".to_string());
+
+        // chunkers are appended after the existing ones, other json files are ignored
+        let account = account.create_chunkers(2).await.unwrap();
+        let account = account.create_chunkers(1).await.unwrap();
+        std::fs::write(keystore_path.join("notes.json"), "{}").unwrap();
+
+        let index = KeystoreIndex::load(&keystore_path).unwrap().unwrap();
+        let indexes: Vec<u32> = index.chunkers.iter().map(|entry| entry.index).collect();
+        assert_eq!(indexes, vec![0, 1, 2]);
+        assert!(index.active().all(|entry| entry.created_at > 0));
+        let addresses: Vec<_> = index.active().map(|entry| entry.address).collect();
+
+        let loaded = account.clone().load_chunkers(None).await.unwrap();
+        let loaded: Vec<_> = loaded
+            .chunkers
+            .unwrap()
+            .iter()
+            .map(|c| c.address())
+            .collect();
+        assert_eq!(loaded, addresses);
+
+        // keystores created before the index are indexed on load
+        std::fs::remove_file(KeystoreIndex::path(&keystore_path)).unwrap();
+        let loaded = account.clone().load_chunkers(Some(2)).await.unwrap();
+        assert_eq!(loaded.chunkers.unwrap().len(), 2);
+        let migrated = KeystoreIndex::load(&keystore_path).unwrap().unwrap();
+        let migrated: Vec<_> = migrated.active().map(|entry| entry.address).collect();
+        assert_eq!(migrated, addresses);
+
+        // corrupt and missing keystores are errors
+        std::fs::write(keystore_path.join("wallet_1.json"), "{}").unwrap();
+        assert!(matches!(
+            account.clone().load_chunkers(None).await,
+            Err(Error::CorruptKeystore { .. })
+        ));
+        std::fs::remove_file(keystore_path.join("wallet_1.json")).unwrap();
+        assert!(matches!(
+            account.clone().load_chunkers(None).await,
+            Err(Error::KeystoreNotFound(_))
+        ));
+
+        // keystores left unindexed by a crash are not overwritten
+        std::fs::write(keystore_path.join("wallet_3.json"), "orphan").unwrap();
+        account.clone().create_chunkers(1).await.unwrap();
+        let orphan = std::fs::read_to_string(keystore_path.join("wallet_3.json")).unwrap();
+        assert_eq!(orphan, "orphan");
+        let index = KeystoreIndex::load(&keystore_path).unwrap().unwrap();
+        assert_eq!(index.chunkers.last().unwrap().index, 4);
+        std::fs::remove_dir_all(&keystore_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hd_chunkers() {
+        let mnemonic = "test test test test test test test test test test test junk".to_string();
+        let addresses = |account: SuperAccount| -> Vec<String> {
+            account
+                .chunkers
+                .unwrap()
+                .iter()
+                .map(|chunker| chunker.address().to_string())
+                .collect()
+        };
+
+        // the same phrase yields the same chunkers on any machine
+        let account = SuperAccount::new().mnemonic(mnemonic.clone());
+        let first = account.clone().load_chunkers(Some(1)).await.unwrap();
+        // the phrase never shows up in debug output
+        let debug = format!("{:?}", LargeBundle::new().super_account(first.clone()));
+        assert!(!debug.contains("junk") && debug.contains("<redacted>"));
+        assert!(debug.contains("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"));
+        assert_eq!(
+            addresses(first),
+            vec!["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"]
+        );
+        let ranged = account
+            .clone()
+            .derivation_range(1..3)
+            .load_chunkers(None)
+            .await
+            .unwrap();
+        assert_eq!(
+            addresses(ranged),
+            vec![
+                "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC"
+            ]
+        );
+
+        // another account of the same seed
+        let other = account
+            .clone()
+            .derivation_path("m/44'/60'/1'/0/".to_string())
+            .load_chunkers(Some(1))
+            .await
+            .unwrap();
+        assert_ne!(
+            addresses(other),
+            vec!["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"]
+        );
+
+        assert!(matches!(
+            account
+                .clone()
+                .derivation_path("not/a/path".to_string())
+                .load_chunkers(Some(1))
+                .await,
+            Err(Error::InvalidMnemonic(_))
+        ));
+        assert!(matches!(
+            SuperAccount::new()
+                .mnemonic("not a mnemonic".to_string())
+                .load_chunkers(Some(1))
+                .await,
+            Err(Error::InvalidMnemonic(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chunker_pool() {
+        let chunkers: Vec<BundlerSigner> = (0..4)
+            .map(|_| BundlerSigner::new(PrivateKeySigner::random()))
+            .collect();
+        let empty = format!("{:#x}", chunkers[3].address());
+
+        // the last chunker holds nothing
+        let rpc = axum::Router::new().route(
+            "/",
+            axum::routing::post(
+                move |axum::Json(request): axum::Json<serde_json::Value>| async move {
+                    let result = match request["method"].as_str() {
+                        Some("eth_getBalance") if request["params"][0] == empty => "0x0",
+                        Some("eth_getBalance") => "0x1",
+                        Some("eth_getTransactionCount") => "0x0",
+                        method => panic!("unexpected {:?}", method),
+                    };
+                    axum::Json(serde_json::json!({
+                        "jsonrpc": "2.0", "id": request["id"], "result": result
+                    }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+
+        let policy = SchedulerPolicy::new().quarantine(Duration::from_millis(50));
+        let pool = ChunkerPool::new(chunkers, policy).unwrap();
+        pool.check_balances(&create_evm_http_client(&url).await.unwrap())
+            .await;
+        let acquire = || async { pool.acquire().await.unwrap().0 };
+
+        // idle chunkers first, then the least used, never the unfunded one
+        assert_eq!(
+            vec![acquire().await, acquire().await, acquire().await],
+            vec![0, 1, 2]
+        );
+        assert_eq!(acquire().await, 0);
+        for slot in [0, 1, 2, 0] {
+            pool.succeeded(slot);
+        }
+
+        // a failing chunker is quarantined, its chunks go to the others
+        assert_eq!(acquire().await, 1);
+        pool.failed(1, &Error::Other("nonce too low".to_string()));
+        assert_eq!(acquire().await, 2);
+        pool.failed(
+            2,
+            &Error::Other("insufficient funds for gas * price + value".to_string()),
+        );
+        assert_eq!(acquire().await, 0);
+        pool.failed(
+            0,
+            &Error::Other("insufficient funds for gas * price + value".to_string()),
+        );
+
+        // only the quarantined chunker is left, it is waited for
+        let started = tokio::time::Instant::now();
+        assert_eq!(acquire().await, 1);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+        pool.failed(
+            1,
+            &Error::Other("insufficient funds for gas * price + value".to_string()),
+        );
+        assert!(matches!(pool.acquire().await, Err(Error::NoFundedChunkers)));
+    }
+
+    #[tokio::test]
+    async fn test_chunker_pool_quarantines_stuck_chunkers() {
+        let chunkers: Vec<BundlerSigner> = (0..2)
+            .map(|_| BundlerSigner::new(PrivateKeySigner::random()))
+            .collect();
+        let stuck = format!("{:#x}", chunkers[0].address());
+
+        // the first chunker has 2 txs waiting in the mempool
+        let rpc = axum::Router::new().route(
+            "/",
+            axum::routing::post(
+                move |axum::Json(request): axum::Json<serde_json::Value>| async move {
+                    let params = &request["params"];
+                    let result = match request["method"].as_str() {
+                        Some("eth_getBalance") => "0x1",
+                        Some("eth_getTransactionCount")
+                            if params[0] == stuck && params[1] == "pending" =>
+                        {
+                            "0x5"
+                        }
+                        Some("eth_getTransactionCount") => "0x3",
+                        method => panic!("unexpected {:?}", method),
+                    };
+                    axum::Json(serde_json::json!({
+                        "jsonrpc": "2.0", "id": request["id"], "result": result
+                    }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+
+        let policy = SchedulerPolicy::new().max_quarantine(Duration::from_millis(100));
+        let pool = ChunkerPool::new(chunkers, policy).unwrap();
+        pool.check_balances(&create_evm_http_client(&url).await.unwrap())
+            .await;
+        let acquire = || async { pool.acquire().await.unwrap().0 };
+
+        // the stuck chunker is skipped until its quarantine ends
+        assert_eq!(vec![acquire().await, acquire().await], vec![1, 1]);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(acquire().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_dispatcher_attempts() {
+        // the node rejects every broadcast
+        let broadcasts = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = broadcasts.clone();
+        let rpc = axum::Router::new().route(
+            "/",
+            axum::routing::post(
+                move |axum::Json(request): axum::Json<serde_json::Value>| async move {
+                    let result = match request["method"].as_str().unwrap_or_default() {
+                        "eth_chainId" => serde_json::json!("0x2518"),
+                        "eth_getBalance" => serde_json::json!("0x1"),
+                        "eth_getTransactionCount" => serde_json::json!("0x0"),
+                        "eth_sendRawTransaction" => {
+                            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                            return axum::Json(serde_json::json!({
+                                "jsonrpc": "2.0",
+                                "id": request["id"],
+                                "error": { "code": -32000, "message": "txpool is full" }
+                            }));
+                        }
+                        _ => serde_json::Value::Null,
+                    };
+                    axum::Json(serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+
+        let chunkers = (0..2)
+            .map(|_| BundlerSigner::new(PrivateKeySigner::random()))
+            .collect();
+        let policy = SchedulerPolicy::new()
+            .max_attempts(3)
+            .quarantine(Duration::from_millis(10));
+        let mut dispatcher =
+            ChunkDispatcher::new(chunkers, NetworkConfig::default().rpc_url(url), policy)
+                .await
+                .unwrap();
+        dispatcher.dispatch(b"chunk".to_vec()).await.unwrap();
+        assert!(dispatcher.finish().await.is_err());
+
+        // the scheduler owns the retries: one broadcast per attempt
+        assert_eq!(broadcasts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fee_policy() {
+        assert_eq!(calldata_gas(&[]), 21_000);
+        assert_eq!(calldata_gas(&[0, 0, 1]), 21_000 + 4 + 4 + 16);
+
+        let network = NetworkConfig::from_toml_str(
+            r#"
+            name = "devnet"
+            rpc_url = "http://127.0.0.1:1"
+            chain_id = 9496
+            babe1 = "0xbabe1d25501157043c7b4ea7CBC877B9B4D8A057"
+            babe2 = "0xbabe2dCAf248F2F1214dF2a471D77bC849a2Ce84"
+
+            [fees]
+            bump_percent = 25
+            max_spend_per_bundle = 100000000000000000
+            "#,
+        )
+        .unwrap();
+        let policy = &network.fees;
+        // unspecified fields fall back to the defaults
+        assert!(policy.estimate);
+        assert_eq!(policy.max_bumps, 5);
+        assert_eq!(FeePolicy::default().bump_percent, 10);
+
+        // the gas limit follows the calldata, within the network cap
+        let calldata = vec![1u8; 1000];
+        assert_eq!(
+            policy.gas_limit(&calldata, &network.gas),
+            37_000 * 110 / 100
+        );
+        assert_eq!(
+            policy.gas_limit(&vec![1u8; 40_000_000], &network.gas),
+            network.gas.bundle_gas_limit
+        );
+
+        // estimation failures fall back to the static fees
+        let provider = create_evm_http_client(&network.rpc_url).await.unwrap();
+        let fees = policy
+            .bundle_fees(&provider, &calldata, &network)
+            .await
+            .unwrap();
+        assert_eq!(fees.max_fee_per_gas, network.gas.max_fee_per_gas);
+        assert_eq!(fees.gas_limit, 40_700);
+
+        // bumps actually raise the fees, even tiny ones
+        let bumped = policy.bump(&fees);
+        assert_eq!(bumped.max_fee_per_gas, fees.max_fee_per_gas * 125 / 100);
+        assert_eq!(
+            FeePolicy::new()
+                .bump(&BundleFees {
+                    max_priority_fee_per_gas: 1,
+                    ..fees
+                })
+                .max_priority_fee_per_gas,
+            2
+        );
+
+        // 40_700 gas at 2 gwei is under the 0.1 tWVM cap, 3B gas is not
+        assert!(policy.check_spend(&fees).is_ok());
+        let large = BundleFees {
+            gas_limit: 3_000_000_000,
+            ..fees
+        };
+        assert!(matches!(
+            policy.check_spend(&large),
+            Err(Error::FeeCapExceeded { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_cost() {
+        // 1 gwei base fee, 0.1 gwei rewards
+        let rpc = axum::Router::new().route(
+            "/",
+            axum::routing::post(
+                |axum::Json(request): axum::Json<serde_json::Value>| async move {
+                    assert_eq!(request["method"], "eth_feeHistory");
+                    axum::Json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": {
+                            "oldestBlock": "0x10",
+                            "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+                            "gasUsedRatio": [0.5],
+                            "reward": [["0x5f5e100"]]
+                        }
+                    }))
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, rpc).await.unwrap() });
+        let network = NetworkConfig::custom(url, 9496);
+        let private_key =
+            "6f142508b4eea641e33cb2a0161221105086a84584c74245ca463a49effea30b".to_string();
+
+        let cost = Bundle::new()
+            .private_key(private_key.clone())
+            .envelopes(vec![Envelope::new()
+                .data(Some(b"hello world".to_vec()))
+                .build()
+                .unwrap()])
+            .network(network.clone())
+            .build()
+            .unwrap()
+            .estimate_cost()
+            .await
+            .unwrap();
+        assert_eq!(cost.base_fee_per_gas, 1_000_000_000);
+        assert!(cost.gas_used > 21_000);
+        assert_eq!(cost.fees.gas_limit, cost.gas_used * 110 / 100);
+        let gas_price = (cost.base_fee_per_gas + cost.fees.max_priority_fee_per_gas)
+            .min(cost.fees.max_fee_per_gas);
+        assert_eq!(cost.expected_wei, cost.gas_used as u128 * gas_price);
+        assert!(cost.max_wei > cost.expected_wei);
+
+        // 3 chunks shared by 2 chunkers
+        let data = generate_random_calldata(8_400_000).into_bytes();
+        let large_bundle = LargeBundle::new()
+            .data(data)
+            .private_key(private_key)
+            .network(network)
+            .super_account(SuperAccount::new())
+            .with_chunkers_count(2)
+            .chunk()
+            .build()
+            .unwrap();
+        let cost = large_bundle.estimate_cost().await.unwrap();
+        assert_eq!(cost.chunks.len(), 3);
+        assert_eq!(cost.manifest.len(), 1);
+        assert!(cost.chunks[0].compressed_size > cost.chunks[2].compressed_size);
+        assert_eq!(
+            cost.expected_wei,
+            cost.chunks
+                .iter()
+                .chain(&cost.manifest)
+                .map(|c| c.expected_wei)
+                .sum::<u128>()
+        );
+        assert_eq!(
+            cost.per_chunker_wei,
+            Some(cost.chunks[0].max_wei + cost.chunks[2].max_wei)
+        );
+
+        // the funding derived from the size alone covers the signed chunks
+        let funding = large_bundle.chunker_funding(8_400_000, 2).await.unwrap();
+        assert!(funding >= cost.per_chunker_wei.unwrap());
+    }
 }
@@ -1,12 +1,21 @@
 pub const CHAIN_ID: u64 = 9496;
 pub const BLOCK_GAS_LIMIT: u128 = 500_000_000;
-pub const GAS_PRIORITY_MULTIPLIER: f32 = 1.1; // 10%
+pub const GAS_PRIORITY_MULTIPLIER: f32 = 1.1; // 10%, default fee bump
+pub const TX_BASE_GAS: u64 = 21_000;
+pub const CALLDATA_ZERO_BYTE_GAS: u64 = 4;
+pub const CALLDATA_NONZERO_BYTE_GAS: u64 = 16;
 pub const TAGS_SIZE_LIMIT: usize = 2048; // in bytes;
+pub const BUNDLE_SIZE_LIMIT: usize = 9 * ONE_MB_IN_BYTES as usize; // compressed bundle data
+pub const SUBMISSION_BODY_LIMIT: usize = 2 * BUNDLE_SIZE_LIMIT + ONE_MB_IN_BYTES as usize; // hex encoded data + JSON overhead
 pub const WVM_RPC_URL: &str = "https://alphanet.load.network";
 pub const LOAD0_ENDPOINT_URL: &str = "https://load0.network";
 pub const ADDRESS_BABE1: &str = "0xbabe1d25501157043c7b4ea7CBC877B9B4D8A057";
 pub const ADDRESS_BABE2: &str = "0xbabe2dCAf248F2F1214dF2a471D77bC849a2Ce84";
 pub const LB_CHUNK_MAX_SIZE: u32 = 4_194_304; // 4MB
+pub const LB_CHUNK_BUNDLE_OVERHEAD: u64 = 4 * ONE_KILOBYTE_IN_BYTES as u64; // envelope fields, tags and brotli framing around a chunk, upper bound
+pub const LB_DOWNLOAD_MAX_CONCURRENT: usize = 8; // chunks retrieved at once
+pub const LB_PROPAGATE_MAX_CONCURRENT: usize = 8; // chunks broadcast at once by a single payer
+pub const LB_SUPER_PROPAGATE_MAX_CONCURRENT: usize = 30; // chunks broadcast at once by the chunkers
 pub const ONE_MB_IN_BYTES: u32 = 1_048_576; // 1MB
 pub const ONE_KILOBYTE_IN_BYTES: u32 = 1024;
 pub const LB_THEORETICAL_SIZE_LIMIT: u64 =
@@ -15,5 +24,10 @@ pub const LB_THEORETICAL_SIZE_LIMIT: u64 =
 pub const LB_SAFE_MAX_SIZE_LIMIT: u64 =
     (2 * ONE_KILOBYTE_IN_BYTES * ONE_KILOBYTE_IN_BYTES * ONE_KILOBYTE_IN_BYTES) as u64; //  2GB
 pub const MAX_THEORETICAL_CHUNKS_IN_LB: u32 = 61680;
+// 61680 chunks of 4MB, just under LB_THEORETICAL_SIZE_LIMIT
+pub const LB_MAX_SIZE_LIMIT: u64 = MAX_THEORETICAL_CHUNKS_IN_LB as u64 * LB_CHUNK_MAX_SIZE as u64;
 pub const MAX_SAFE_CHUNKS_IN_LB: u32 = 2 * 256; // 2GB
 pub const SAFE_CHUNK_TOPUP: u64 = 1_000_000_000_000_000_000;
+pub const HD_DERIVATION_PATH: &str = "m/44'/60'/0'/0"; // chunker i at m/44'/60'/0'/0/i
+pub const HD_CHUNKERS_COUNT: u32 = 256; // derived when no range is given
+pub const MAX_NONCE_RESYNCS: u32 = 5; // "nonce too low" retries of a single broadcast
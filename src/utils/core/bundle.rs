@@ -1,30 +1,66 @@
-use crate::utils::constants::ADDRESS_BABE1;
 use crate::utils::core::bundle_data::BundleData;
 use crate::utils::core::bundle_tx_metadata::BundleTxMetadata;
+use crate::utils::core::bundle_validator::BundleValidator;
+use crate::utils::core::cost_estimate::BundleCost;
 use crate::utils::core::envelope::Envelope;
+use crate::utils::core::signed_bundle::{BundleFees, EncodedBundle, SignedBundle};
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::core::verified_bundle::{VerificationMode, VerifiedBundleData};
 use crate::utils::errors::Error;
-use crate::utils::evm::{create_bundle, retrieve_bundle_data, retrieve_bundle_tx};
+use crate::utils::evm::{
+    broadcast_bundle, create_evm_http_client, encode_bundle, encode_raw_tx, retrieve_bundle_data,
+    retrieve_bundle_tx, sign_bundle_tx, sign_envelopes, submit_raw_tx,
+};
+use crate::utils::fees::next_base_fee;
 use crate::utils::load0::upload_to_load0;
+use crate::utils::network::NetworkConfig;
+use crate::utils::signer::BundlerSigner;
 
 #[derive(Debug, Default)]
 pub struct Bundle {
     pub envelopes: Option<Vec<Envelope>>,
+    // envelopes already signed by their owners, settled by the payer
+    pub signed_envelopes: Option<Vec<TxEnvelopeWrapper>>,
     pub private_key: Option<String>,
+    // signs the envelopes, defaults to the private key signer
+    pub signer: Option<BundlerSigner>,
+    // signs and pays for the bundle tx, defaults to the envelopes signer
+    pub payer: Option<BundlerSigner>,
+    pub network: NetworkConfig,
 }
 
 impl Bundle {
     pub fn new() -> Self {
         Bundle {
             envelopes: None,
+            signed_envelopes: None,
             private_key: None,
+            signer: None,
+            payer: None,
+            network: NetworkConfig::default(),
         }
     }
 
+    pub fn network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
     pub fn private_key(mut self, key: String) -> Self {
         self.private_key = Some(key);
         self
     }
 
+    pub fn signer(mut self, signer: BundlerSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    pub fn payer(mut self, payer: BundlerSigner) -> Self {
+        self.payer = Some(payer);
+        self
+    }
+
     pub fn envelopes(mut self, envelopes: Vec<Envelope>) -> Self {
         self.envelopes = Some(envelopes);
         self
@@ -35,38 +71,171 @@ impl Bundle {
         self
     }
 
+    pub fn signed_envelopes(mut self, envelopes: Vec<TxEnvelopeWrapper>) -> Self {
+        self.signed_envelopes = Some(envelopes);
+        self
+    }
+
+    pub fn add_signed_envelope(mut self, envelope: TxEnvelopeWrapper) -> Self {
+        self.signed_envelopes
+            .get_or_insert(Vec::new())
+            .push(envelope);
+        self
+    }
+
     pub fn build(self) -> Result<Bundle, Error> {
-        let envelopes = self
-            .envelopes
-            .filter(|e| !e.is_empty())
-            .ok_or(Error::EnvelopesNeeded)?;
-        let private_key = self
-            .private_key
-            .filter(|p| !p.is_empty())
+        let envelopes = self.envelopes.filter(|e| !e.is_empty());
+        let signed_envelopes = self.signed_envelopes.filter(|e| !e.is_empty());
+        if envelopes.is_none() && signed_envelopes.is_none() {
+            return Err(Error::EnvelopesNeeded);
+        }
+
+        // pre-signed envelopes are only accepted if they pass the envelope rules
+        if let Some(signed_envelopes) = &signed_envelopes {
+            let validator = BundleValidator::new(&self.network);
+            for (index, envelope) in signed_envelopes.iter().enumerate() {
+                validator.verify_envelope(index, envelope)?;
+            }
+        }
+
+        let private_key = self.private_key.filter(|p| !p.is_empty());
+        let signer = match (self.signer, private_key.as_deref()) {
+            (Some(signer), _) => Some(signer),
+            (None, Some(key)) => Some(BundlerSigner::from_private_key(key)?),
+            (None, None) => None,
+        };
+        // a signer is only needed for envelopes the bundle signs itself
+        if envelopes.is_some() && signer.is_none() {
+            return Err(Error::PrivateKeyNeeded);
+        }
+        let payer = self
+            .payer
+            .or_else(|| signer.clone())
             .ok_or(Error::PrivateKeyNeeded)?;
 
         Ok(Bundle {
-            envelopes: Some(envelopes),
-            private_key: Some(private_key),
+            envelopes,
+            signed_envelopes,
+            private_key,
+            signer,
+            payer: Some(payer),
+            network: self.network,
         })
     }
+
+    fn resolved_payer(&self) -> Result<&BundlerSigner, Error> {
+        self.payer
+            .as_ref()
+            .or(self.signer.as_ref())
+            .ok_or(Error::PrivateKeyNeeded)
+    }
+
     pub async fn propagate(self) -> Result<String, Error> {
-        let envelopes = self.envelopes.ok_or(Error::EnvelopesNeeded)?;
-        let private_key = self.private_key.ok_or(Error::PrivateKeyNeeded)?;
+        let babe1 = self.network.babe1.clone();
+        self.propagate_to(&babe1).await
+    }
 
-        let tx = create_bundle(None, envelopes, private_key, ADDRESS_BABE1)
-            .await
-            .map_err(|_| Error::BundleNotCreated)?;
+    /// Propagates the bundle to the `version` address, `network.babe1` or
+    /// `network.babe2`, which retrieval later checks the bundle against.
+    pub async fn propagate_to(self, version: &str) -> Result<String, Error> {
+        let payer = self.resolved_payer()?;
+        let encoded = self.encode().await?;
+        let provider = create_evm_http_client(&self.network.rpc_url).await?;
+
+        let tx =
+            broadcast_bundle(encoded.compressed, &provider, payer, version, &self.network).await?;
         let hash = tx.tx_hash().to_string();
         Ok(hash)
     }
 
+    /// Signs the envelopes, appends the pre-signed ones and returns the
+    /// serialized and compressed bundle data, without touching the network.
+    pub async fn encode(&self) -> Result<EncodedBundle, Error> {
+        let mut wrappers = Vec::new();
+
+        if let Some(envelopes) = self.envelopes.clone() {
+            let signer = self.signer.as_ref().ok_or(Error::PrivateKeyNeeded)?;
+            wrappers = sign_envelopes(envelopes, signer, &self.network).await?;
+        }
+        wrappers.extend(self.signed_envelopes.clone().unwrap_or_default());
+
+        if wrappers.is_empty() {
+            return Err(Error::EnvelopesNeeded);
+        }
+        encode_bundle(wrappers, &self.network, false)
+    }
+
+    /// Estimates what propagating this built bundle costs at the current
+    /// fees, without sending it.
+    pub async fn estimate_cost(&self) -> Result<BundleCost, Error> {
+        let encoded = self.encode().await?;
+        let provider = create_evm_http_client(&self.network.rpc_url).await?;
+        let quote = self
+            .network
+            .fees
+            .estimate_fees(&provider, &encoded.compressed, &self.network)
+            .await;
+        let base_fee = next_base_fee(&provider).await?;
+
+        Ok(BundleCost::new(
+            &encoded.compressed,
+            &quote,
+            base_fee,
+            &self.network,
+        ))
+    }
+
+    /// Encodes the bundle and signs the outer EIP-1559 transaction offline,
+    /// with the caller-provided nonce and fees.
+    pub async fn build_signed(&self, nonce: u64, fees: BundleFees) -> Result<SignedBundle, Error> {
+        self.build_signed_to(&self.network.babe1, nonce, fees).await
+    }
+
+    /// `build_signed` for the `version` address, `network.babe1` or
+    /// `network.babe2`, as `propagate_to` does online.
+    pub async fn build_signed_to(
+        &self,
+        version: &str,
+        nonce: u64,
+        fees: BundleFees,
+    ) -> Result<SignedBundle, Error> {
+        let payer = self.resolved_payer()?;
+        let encoded = self.encode().await?;
+
+        let tx = sign_bundle_tx(
+            encoded.compressed.clone(),
+            payer,
+            version,
+            nonce,
+            &fees,
+            &self.network,
+        )
+        .await?;
+
+        Ok(SignedBundle {
+            encoded,
+            nonce,
+            fees,
+            tx_hash: tx.tx_hash().to_string(),
+            raw_tx: encode_raw_tx(&tx),
+        })
+    }
+
+    /// Broadcasts a transaction produced by `build_signed`, returning its hash.
+    pub async fn submit_raw(raw_tx: &[u8], network: &NetworkConfig) -> Result<String, Error> {
+        let provider = create_evm_http_client(&network.rpc_url).await?;
+        let tx = submit_raw_tx(&provider, raw_tx).await?;
+        Ok(tx.tx_hash().to_string())
+    }
+
     pub async fn propagate_to_load0(self, api_key: Option<String>) -> Result<String, Error> {
         // for now envelope content-type is set to default (octet-stream) and it support single-envelope
         // bundle type only.
         let envelopes = self.envelopes.ok_or(Error::EnvelopesNeeded)?;
-        let envelope: Envelope = envelopes.get(0).ok_or(Error::EnvelopesNeeded).cloned()?;
-        let load0_tx = upload_to_load0(envelope.data.unwrap_or_default(), None, api_key).await.map_err(|_| Error::BundleNotCreated)?;
+        let envelope: Envelope = envelopes.first().ok_or(Error::EnvelopesNeeded).cloned()?;
+        let load0_tx = upload_to_load0(envelope.data.unwrap_or_default(), None, api_key)
+            .await
+            .map_err(|_| Error::BundleNotCreated)?;
         Ok(load0_tx)
     }
 
@@ -74,15 +243,47 @@ impl Bundle {
         bundle_txid: String,
         version: &str,
     ) -> Result<BundleData, Error> {
-        let bundle: BundleTxMetadata = retrieve_bundle_tx(bundle_txid)
-            .await
-            .map_err(|_| Error::BundleRetrievalProblem)?;
+        Self::retrieve_envelopes_with_network(bundle_txid, version, &NetworkConfig::default()).await
+    }
+
+    pub async fn retrieve_envelopes_with_network(
+        bundle_txid: String,
+        version: &str,
+        network: &NetworkConfig,
+    ) -> Result<BundleData, Error> {
+        let bundle: BundleTxMetadata = retrieve_bundle_tx(bundle_txid, network).await?;
         // assert the bundle versioning by checking target address
         if bundle.to.to_lowercase() != version.to_ascii_lowercase() {
             return Err(Error::UnverifiedAddress);
         }
 
-        let res: BundleData = retrieve_bundle_data(bundle.calldata).await;
+        let res: BundleData = retrieve_bundle_data(bundle.calldata).await?;
         Ok(res)
     }
+
+    /// Retrieves a bundle and verifies the hash and signature of every
+    /// envelope, rejecting or flagging the ones that fail.
+    pub async fn retrieve_verified_envelopes(
+        bundle_txid: String,
+        version: &str,
+        mode: VerificationMode,
+    ) -> Result<VerifiedBundleData, Error> {
+        Self::retrieve_verified_envelopes_with_network(
+            bundle_txid,
+            version,
+            mode,
+            &NetworkConfig::default(),
+        )
+        .await
+    }
+
+    pub async fn retrieve_verified_envelopes_with_network(
+        bundle_txid: String,
+        version: &str,
+        mode: VerificationMode,
+        network: &NetworkConfig,
+    ) -> Result<VerifiedBundleData, Error> {
+        let bundle = Self::retrieve_envelopes_with_network(bundle_txid, version, network).await?;
+        VerifiedBundleData::verify(bundle, mode)
+    }
 }
@@ -3,6 +3,8 @@ use crate::utils::core::envelope_with_owner::{BundleDataWithOwner, TxEnvelopeWra
 use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
 use crate::utils::errors::Error;
 use crate::utils::evm::create_envelope;
+use crate::utils::network::NetworkConfig;
+use crate::utils::signer::BundlerSigner;
 use alloy::consensus::TxEnvelope;
 use borsh_derive::{BorshDeserialize, BorshSerialize};
 
@@ -25,17 +27,24 @@ impl BundleData {
     }
 
     pub async fn create_envelope(
-        private_key: Option<&str>,
+        signer: &BundlerSigner,
         envelope: Envelope,
+        network: &NetworkConfig,
     ) -> Result<TxEnvelope, Error> {
-        create_envelope(private_key, envelope).await
+        create_envelope(signer, envelope, network).await
     }
 
-    pub async fn to_bundle_with_owners(bundle: BundleData) -> BundleDataWithOwner {
+    pub async fn to_bundle_with_owners(bundle: BundleData) -> Result<BundleDataWithOwner, Error> {
         let mut envelopes: Vec<TxEnvelopeWrapperWithOwner> = Vec::new();
-        for envelope in bundle.envelopes {
-            envelopes.push(TxEnvelopeWrapperWithOwner::from(envelope.clone()).await);
+        for (index, envelope) in bundle.envelopes.into_iter().enumerate() {
+            let envelope = TxEnvelopeWrapperWithOwner::from(envelope)
+                .await
+                .map_err(|e| Error::EnvelopeRuleViolation {
+                    index,
+                    rule: format!("envelope MUST have a valid signature ({})", e),
+                })?;
+            envelopes.push(envelope);
         }
-        BundleDataWithOwner::from(envelopes).await
+        Ok(BundleDataWithOwner::from(envelopes).await)
     }
 }
@@ -0,0 +1,275 @@
+use crate::utils::constants::{BUNDLE_SIZE_LIMIT, TAGS_SIZE_LIMIT};
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use crate::utils::evm::{decode_bundle_data, retrieve_bundle_tx};
+use crate::utils::network::NetworkConfig;
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Violation {
+    NonZeroNonce { nonce: u64 },
+    NonZeroGasLimit { gas_limit: u64 },
+    NonZeroGasPrice { gas_price: u128 },
+    NonZeroValue { value: String },
+    ChainIdMismatch { expected: u64, found: u64 },
+    TagsTooLarge { size: usize, limit: usize },
+    MalformedEnvelope { reason: String },
+    InvalidSignature { reason: String },
+    HashMismatch { stored: String, computed: String },
+    EmptyBundle,
+    BundleTooLarge { size: usize, limit: usize },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::NonZeroNonce { nonce } => write!(f, "nonce MUST be 0 (found {})", nonce),
+            Violation::NonZeroGasLimit { gas_limit } => {
+                write!(f, "gas_limit MUST be 0 (found {})", gas_limit)
+            }
+            Violation::NonZeroGasPrice { gas_price } => {
+                write!(f, "gas_price MUST be 0 (found {})", gas_price)
+            }
+            Violation::NonZeroValue { value } => write!(f, "value MUST be 0 (found {})", value),
+            Violation::ChainIdMismatch { expected, found } => {
+                write!(f, "chain_id MUST be {} (found {})", expected, found)
+            }
+            Violation::TagsTooLarge { size, limit } => {
+                write!(f, "tags MUST be <= {} bytes (found {})", limit, size)
+            }
+            Violation::MalformedEnvelope { reason } => write!(f, "malformed envelope: {}", reason),
+            Violation::InvalidSignature { reason } => {
+                write!(f, "envelope MUST have a valid signature ({})", reason)
+            }
+            Violation::HashMismatch { stored, computed } => write!(
+                f,
+                "stored hash {} does not match computed hash {}",
+                stored, computed
+            ),
+            Violation::EmptyBundle => write!(f, "bundle MUST have envelopes"),
+            Violation::BundleTooLarge { size, limit } => write!(
+                f,
+                "compressed bundle MUST be under {} bytes (found {})",
+                limit, size
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnvelopeReport {
+    pub index: usize,
+    pub signer: Option<String>,
+    pub stored_hash: String,
+    pub computed_hash: Option<String>,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub compressed_size: Option<usize>,
+    pub violations: Vec<Violation>,
+    pub envelopes: Vec<EnvelopeReport>,
+}
+
+impl ValidationReport {
+    /// Turns the first violation found into an `Error`, bundle-level first.
+    pub fn into_result(self) -> Result<Self, Error> {
+        if let Some(violation) = self.violations.first() {
+            return Err(match violation {
+                Violation::BundleTooLarge { size, .. } => Error::BundleTooLarge(*size),
+                Violation::EmptyBundle => Error::EnvelopesNeeded,
+                other => Error::EnvelopeVerification(other.to_string()),
+            });
+        }
+
+        if let Some(envelope) = self.envelopes.iter().find(|e| !e.violations.is_empty()) {
+            return Err(Error::EnvelopeRuleViolation {
+                index: envelope.index,
+                rule: envelope.violations[0].to_string(),
+            });
+        }
+
+        Ok(self)
+    }
+}
+
+/// Checks bundles and envelopes against every MUST of the Bundler spec.
+#[derive(Debug, Clone)]
+pub struct BundleValidator {
+    pub chain_id: u64,
+    pub size_limit: usize,
+    pub tags_size_limit: usize,
+}
+
+impl Default for BundleValidator {
+    fn default() -> Self {
+        Self::new(&NetworkConfig::default())
+    }
+}
+
+impl BundleValidator {
+    pub fn new(network: &NetworkConfig) -> Self {
+        Self {
+            chain_id: network.chain_id,
+            size_limit: BUNDLE_SIZE_LIMIT,
+            tags_size_limit: TAGS_SIZE_LIMIT,
+        }
+    }
+
+    pub fn validate_envelope(&self, index: usize, envelope: &TxEnvelopeWrapper) -> EnvelopeReport {
+        let mut violations = Vec::new();
+
+        if envelope.nonce != 0 {
+            violations.push(Violation::NonZeroNonce {
+                nonce: envelope.nonce,
+            });
+        }
+        if envelope.gas_limit != 0 {
+            violations.push(Violation::NonZeroGasLimit {
+                gas_limit: envelope.gas_limit,
+            });
+        }
+        if envelope.gas_price != 0 {
+            violations.push(Violation::NonZeroGasPrice {
+                gas_price: envelope.gas_price,
+            });
+        }
+        if U256::from_str(&envelope.value).map_or(true, |v| !v.is_zero()) {
+            violations.push(Violation::NonZeroValue {
+                value: envelope.value.clone(),
+            });
+        }
+        if envelope.chain_id != self.chain_id {
+            violations.push(Violation::ChainIdMismatch {
+                expected: self.chain_id,
+                found: envelope.chain_id,
+            });
+        }
+
+        let tags = envelope.tags.clone().unwrap_or_default();
+        let tags_size = serde_json::to_string(&tags).map_or(0, |t| t.len());
+        if tags_size > self.tags_size_limit {
+            violations.push(Violation::TagsTooLarge {
+                size: tags_size,
+                limit: self.tags_size_limit,
+            });
+        }
+
+        let mut signer = None;
+        let mut computed_hash = None;
+
+        match envelope.to_signed_legacy() {
+            Ok(signed) => {
+                let computed = signed.hash().to_string();
+                if !computed.eq_ignore_ascii_case(&envelope.hash) {
+                    violations.push(Violation::HashMismatch {
+                        stored: envelope.hash.clone(),
+                        computed: computed.clone(),
+                    });
+                }
+                computed_hash = Some(computed);
+
+                match signed.recover_signer() {
+                    Ok(address) => signer = Some(address.to_checksum(Some(envelope.chain_id))),
+                    Err(e) => violations.push(Violation::InvalidSignature {
+                        reason: e.to_string(),
+                    }),
+                }
+            }
+            Err(e) => violations.push(Violation::MalformedEnvelope {
+                reason: e.to_string(),
+            }),
+        }
+
+        EnvelopeReport {
+            index,
+            signer,
+            stored_hash: envelope.hash.clone(),
+            computed_hash,
+            violations,
+        }
+    }
+
+    /// Accepts a pre-signed envelope only if it passes every envelope rule,
+    /// returning its recovered owner.
+    pub fn verify_envelope(
+        &self,
+        index: usize,
+        envelope: &TxEnvelopeWrapper,
+    ) -> Result<String, Error> {
+        let report = self.validate_envelope(index, envelope);
+        if let Some(violation) = report.violations.first() {
+            return Err(Error::EnvelopeRuleViolation {
+                index,
+                rule: violation.to_string(),
+            });
+        }
+        report.signer.ok_or(Error::EnvelopeRuleViolation {
+            index,
+            rule: "envelope MUST have a valid signature".to_string(),
+        })
+    }
+
+    /// `compressed_size` is the Borsh-Brotli calldata size when known.
+    pub fn validate(
+        &self,
+        bundle: &BundleData,
+        compressed_size: Option<usize>,
+    ) -> ValidationReport {
+        let mut violations = Vec::new();
+
+        if bundle.envelopes.is_empty() {
+            violations.push(Violation::EmptyBundle);
+        }
+        if let Some(size) = compressed_size.filter(|size| *size >= self.size_limit) {
+            violations.push(Violation::BundleTooLarge {
+                size,
+                limit: self.size_limit,
+            });
+        }
+
+        let envelopes: Vec<EnvelopeReport> = bundle
+            .envelopes
+            .iter()
+            .enumerate()
+            .map(|(index, envelope)| self.validate_envelope(index, envelope))
+            .collect();
+
+        let valid = violations.is_empty() && envelopes.iter().all(|e| e.violations.is_empty());
+
+        ValidationReport {
+            valid,
+            compressed_size,
+            violations,
+            envelopes,
+        }
+    }
+
+    pub fn validate_calldata(&self, calldata: &str) -> Result<ValidationReport, Error> {
+        let compressed_size = calldata.trim_start_matches("0x").len() / 2;
+        let bundle = decode_bundle_data(calldata)?;
+        Ok(self.validate(&bundle, Some(compressed_size)))
+    }
+
+    /// Retrieves a bundle tx from the network and validates its content.
+    pub async fn validate_bundle_tx(
+        &self,
+        bundle_txid: String,
+        network: &NetworkConfig,
+    ) -> Result<ValidationReport, Error> {
+        let bundle = retrieve_bundle_tx(bundle_txid, network).await?;
+        let to = bundle.to.to_ascii_lowercase();
+        if to != network.babe1.to_ascii_lowercase() && to != network.babe2.to_ascii_lowercase() {
+            return Err(Error::UnverifiedAddress);
+        }
+
+        self.validate_calldata(&bundle.calldata)
+    }
+}
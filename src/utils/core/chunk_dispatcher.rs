@@ -0,0 +1,238 @@
+use crate::utils::core::chunker_scheduler::{ChunkerPool, SchedulerPolicy};
+use crate::utils::core::large_bundle::LargeBundle;
+use crate::utils::core::large_bundle_manifest::{normalize_txid, ChunkEntry, LargeBundleManifest};
+use crate::utils::core::upload_journal::SharedJournal;
+use crate::utils::errors::Error;
+use crate::utils::evm::{create_evm_http_client, HttpClient};
+use crate::utils::network::NetworkConfig;
+use crate::utils::signer::BundlerSigner;
+use alloy::primitives::{keccak256, Keccak256};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Propagates Large Bundle chunks as they are produced, each through the
+/// least busy funded chunker. A failed chunk is reassigned to another chunker
+/// while the failing one is quarantined. At most `max_concurrent` chunks are
+/// held in flight, `dispatch` waits for a free slot, so the caller never
+/// buffers the whole data.
+pub struct ChunkDispatcher {
+    pool: Arc<ChunkerPool>,
+    network: NetworkConfig,
+    http_client: HttpClient,
+    semaphore: Arc<Semaphore>,
+    tasks: JoinSet<Result<(usize, String, BundlerSigner), Error>>,
+    // (size, keccak256) of each dispatched chunk, and of the whole data
+    entries: Vec<(u64, String)>,
+    receipts: Vec<Option<String>>,
+    // envelope signer of each chunk, unknown for the journaled ones of chunkers
+    signers: Vec<Option<BundlerSigner>>,
+    hasher: Keccak256,
+    journal: Option<SharedJournal>,
+    // signs every chunk envelope, each chunker signs its own otherwise
+    signer: Option<BundlerSigner>,
+}
+
+impl ChunkDispatcher {
+    pub async fn new(
+        chunkers: Vec<BundlerSigner>,
+        network: NetworkConfig,
+        policy: SchedulerPolicy,
+    ) -> Result<Self, Error> {
+        let max_concurrent = policy.max_concurrent;
+        let pool = ChunkerPool::new(chunkers, policy)?;
+        let http_client = create_evm_http_client(&network.rpc_url)
+            .await
+            .map_err(|err| Error::Other(err.to_string()))?;
+        pool.check_balances(&http_client).await;
+
+        Ok(Self {
+            pool: Arc::new(pool),
+            network,
+            http_client,
+            semaphore: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            tasks: JoinSet::new(),
+            entries: Vec::new(),
+            receipts: Vec::new(),
+            signers: Vec::new(),
+            hasher: Keccak256::new(),
+            journal: None,
+            signer: None,
+        })
+    }
+
+    /// Signs every chunk envelope with `signer`, the chunkers only pay for
+    /// the chunk bundles.
+    pub fn signer(mut self, signer: BundlerSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Records each chunk in the journal as it lands, and skips the chunks a
+    /// previous run already recorded for the same data.
+    pub fn journal(mut self, journal: SharedJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn dispatched(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub async fn dispatch(&mut self, chunk: Vec<u8>) -> Result<(), Error> {
+        // surface failures early instead of after the whole upload
+        while let Some(result) = self.tasks.try_join_next() {
+            let (index, txid, signer) = self.check(result)?;
+            self.receipts[index] = Some(txid);
+            self.signers[index] = Some(signer);
+        }
+
+        let chunk_index = self.entries.len();
+        let size = chunk.len() as u64;
+        let hash = keccak256(&chunk).to_string();
+        self.hasher.update(&chunk);
+        self.entries.push((size, hash.clone()));
+
+        let confirmed = self.journal.as_ref().and_then(|journal| {
+            journal
+                .confirmed(chunk_index, &hash)
+                .map(|entry| normalize_txid(&entry.txid))
+        });
+        if let Some(txid) = confirmed {
+            println!("Chunk {} already propagated in {}", chunk_index, txid);
+            self.receipts.push(Some(txid));
+            self.signers.push(self.signer.clone());
+            return Ok(());
+        }
+        self.receipts.push(None);
+        self.signers.push(None);
+
+        let permit = Arc::clone(&self.semaphore)
+            .acquire_owned()
+            .await
+            .map_err(|e| Error::Other(e.to_string()))?;
+
+        let pool = Arc::clone(&self.pool);
+        let http_client = self.http_client.clone();
+        let network = self.network.clone();
+        let journal = self.journal.clone();
+        let signer = self.signer.clone();
+
+        self.tasks.spawn(async move {
+            let _permit = permit;
+            let (txid, signer) = Self::propagate(
+                &pool,
+                signer.as_ref(),
+                chunk_index,
+                chunk,
+                http_client,
+                &network,
+            )
+            .await?;
+            if let Some(journal) = journal {
+                let entry = ChunkEntry {
+                    txid: txid.clone(),
+                    size,
+                    hash,
+                };
+                journal.record_chunk(chunk_index, entry).await?;
+            }
+            Ok((chunk_index, txid, signer))
+        });
+
+        Ok(())
+    }
+
+    /// Sends a chunk through the chunkers the pool picks until one succeeds,
+    /// or `max_attempts` chunkers failed. Returns the chunk txid and the
+    /// signer of its envelope.
+    async fn propagate(
+        pool: &ChunkerPool,
+        signer: Option<&BundlerSigner>,
+        chunk_index: usize,
+        chunk: Vec<u8>,
+        http_client: HttpClient,
+        network: &NetworkConfig,
+    ) -> Result<(String, BundlerSigner), Error> {
+        let mut attempts = 0;
+        loop {
+            let (slot, chunker) = pool.acquire().await?;
+            let signer = signer.unwrap_or(&chunker);
+            match LargeBundle::propagate_chunk_once(
+                chunk_index,
+                chunk.clone(),
+                signer,
+                &chunker,
+                http_client.clone(),
+                network,
+            )
+            .await
+            {
+                Ok(txid) => {
+                    pool.succeeded(slot);
+                    return Ok((txid, signer.clone()));
+                }
+                Err(e) => {
+                    pool.failed(slot, &e);
+                    attempts += 1;
+                    if attempts >= pool.policy().max_attempts {
+                        return Err(e);
+                    }
+                    println!(
+                        "Chunk {} failed with chunker {}: {}, reassigning",
+                        chunk_index,
+                        chunker.address(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Waits for every dispatched chunk and returns the manifest of the
+    /// propagated chunks, in chunk order, with the envelope signer of each.
+    pub async fn finish(
+        mut self,
+    ) -> Result<(LargeBundleManifest, Vec<Option<BundlerSigner>>), Error> {
+        while let Some(result) = self.tasks.join_next().await {
+            let (index, txid, signer) = self.check(result)?;
+            self.receipts[index] = Some(txid);
+            self.signers[index] = Some(signer);
+        }
+
+        let chunks = self
+            .receipts
+            .into_iter()
+            .zip(self.entries)
+            .enumerate()
+            .map(|(i, (txid, (size, hash)))| {
+                let txid =
+                    txid.ok_or_else(|| Error::Other(format!("Missing receipt for chunk {}", i)))?;
+                Ok(ChunkEntry { txid, size, hash })
+            })
+            .collect::<Result<Vec<ChunkEntry>, Error>>()?;
+        let chunk_size = chunks.first().map_or(0, |c| c.size);
+
+        let manifest =
+            LargeBundleManifest::new(chunks, chunk_size, self.hasher.finalize().to_string());
+        Ok((manifest, self.signers))
+    }
+
+    fn check(
+        &mut self,
+        result: Result<Result<(usize, String, BundlerSigner), Error>, tokio::task::JoinError>,
+    ) -> Result<(usize, String, BundlerSigner), Error> {
+        match result {
+            Ok(Ok(receipt)) => Ok(receipt),
+            Ok(Err(e)) => {
+                println!("Error processing chunk: {}", e);
+                self.tasks.abort_all();
+                Err(e)
+            }
+            Err(e) => {
+                self.tasks.abort_all();
+                Err(Error::Other(e.to_string()))
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::utils::constants::LB_SUPER_PROPAGATE_MAX_CONCURRENT;
+use crate::utils::errors::Error;
+use crate::utils::evm::HttpClient;
+use crate::utils::signer::BundlerSigner;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use std::future::IntoFuture;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How Large Bundle chunks are spread over the chunkers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerPolicy {
+    // chunks in flight at once, across all chunkers
+    pub max_concurrent: usize,
+    // chunkers a chunk is tried on before the upload fails
+    pub max_attempts: u32,
+    // first quarantine of a failing chunker, doubled on each failure in a row
+    pub quarantine: Duration,
+    pub max_quarantine: Duration,
+}
+
+impl Default for SchedulerPolicy {
+    fn default() -> Self {
+        SchedulerPolicy {
+            max_concurrent: LB_SUPER_PROPAGATE_MAX_CONCURRENT,
+            max_attempts: 5,
+            quarantine: Duration::from_secs(2),
+            max_quarantine: Duration::from_secs(60),
+        }
+    }
+}
+
+impl SchedulerPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent;
+        self
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn quarantine(mut self, quarantine: Duration) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    pub fn max_quarantine(mut self, max_quarantine: Duration) -> Self {
+        self.max_quarantine = max_quarantine;
+        self
+    }
+
+    fn quarantine_for(&self, failures: u32) -> Duration {
+        let doublings = failures.saturating_sub(1).min(16);
+        self.quarantine
+            .saturating_mul(1 << doublings)
+            .min(self.max_quarantine)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChunkerState {
+    in_flight: usize,
+    // chunks handed to the chunker so far, spreads the work evenly
+    assigned: usize,
+    // failures in a row
+    failures: u32,
+    quarantined_until: Option<Instant>,
+    // out of funds, never picked again
+    unfunded: bool,
+}
+
+/// Hands out the least busy chunker that is funded and not quarantined.
+/// A chunker whose chunk failed is quarantined for a while, so its chunks
+/// go to the other chunkers until it recovers.
+#[derive(Debug)]
+pub struct ChunkerPool {
+    chunkers: Vec<BundlerSigner>,
+    states: Mutex<Vec<ChunkerState>>,
+    policy: SchedulerPolicy,
+}
+
+impl ChunkerPool {
+    pub fn new(chunkers: Vec<BundlerSigner>, policy: SchedulerPolicy) -> Result<Self, Error> {
+        if chunkers.is_empty() {
+            return Err(Error::ChunkersNeeded);
+        }
+
+        Ok(Self {
+            states: Mutex::new(vec![ChunkerState::default(); chunkers.len()]),
+            chunkers,
+            policy,
+        })
+    }
+
+    pub fn policy(&self) -> &SchedulerPolicy {
+        &self.policy
+    }
+
+    /// Sets aside the chunkers holding no balance, and quarantines the ones
+    /// with txs stuck in the mempool, whose pending nonce is ahead of their
+    /// latest one: their chunks would queue behind the stuck txs. Balances
+    /// and nonces that can't be read are assumed fine.
+    pub async fn check_balances(&self, provider: &HttpClient) {
+        let checks = futures::future::join_all(self.chunkers.iter().map(|chunker| async move {
+            let address = chunker.address();
+            let balance = provider.get_balance(address).into_future().await;
+            let latest = provider
+                .get_transaction_count(address)
+                .latest()
+                .into_future();
+            let pending = provider
+                .get_transaction_count(address)
+                .pending()
+                .into_future();
+            let stuck = matches!(
+                tokio::join!(latest, pending),
+                (Ok(latest), Ok(pending)) if pending > latest
+            );
+            (balance, stuck)
+        }))
+        .await;
+
+        let mut states = self.states.lock().unwrap();
+        let now = Instant::now();
+        for (state, (balance, stuck)) in states.iter_mut().zip(checks) {
+            if matches!(balance, Ok(balance) if balance == U256::ZERO) {
+                state.unfunded = true;
+            } else if stuck {
+                state.quarantined_until = Some(now + self.policy.max_quarantine);
+            }
+        }
+    }
+
+    /// Picks a chunker, waiting for a quarantine to end if every funded
+    /// chunker is quarantined. Fails once no funded chunker is left.
+    pub async fn acquire(&self) -> Result<(usize, BundlerSigner), Error> {
+        loop {
+            let wait_until = {
+                let mut states = self.states.lock().unwrap();
+                let now = Instant::now();
+                let available = states
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, state)| !state.unfunded)
+                    .filter(|(_, state)| state.quarantined_until.is_none_or(|until| until <= now))
+                    .min_by_key(|(_, state)| (state.in_flight, state.assigned))
+                    .map(|(slot, _)| slot);
+
+                if let Some(slot) = available {
+                    let state = &mut states[slot];
+                    state.in_flight += 1;
+                    state.assigned += 1;
+                    return Ok((slot, self.chunkers[slot].clone()));
+                }
+
+                states
+                    .iter()
+                    .filter(|state| !state.unfunded)
+                    .filter_map(|state| state.quarantined_until)
+                    .min()
+                    .ok_or(Error::NoFundedChunkers)?
+            };
+
+            tokio::time::sleep_until(wait_until).await;
+        }
+    }
+
+    /// The chunk sent by the chunker in `slot` was accepted.
+    pub fn succeeded(&self, slot: usize) {
+        let mut states = self.states.lock().unwrap();
+        let state = &mut states[slot];
+        state.in_flight = state.in_flight.saturating_sub(1);
+        state.failures = 0;
+        state.quarantined_until = None;
+    }
+
+    /// The chunk sent by the chunker in `slot` failed with `error`: the
+    /// chunker is set aside, for good if it ran out of funds.
+    pub fn failed(&self, slot: usize, error: &Error) {
+        let mut states = self.states.lock().unwrap();
+        let state = &mut states[slot];
+        state.in_flight = state.in_flight.saturating_sub(1);
+        state.failures += 1;
+
+        if Self::out_of_funds(error) {
+            state.unfunded = true;
+        } else {
+            state.quarantined_until =
+                Some(Instant::now() + self.policy.quarantine_for(state.failures));
+        }
+    }
+
+    fn out_of_funds(error: &Error) -> bool {
+        // the node's message is not part of these variants' display
+        let message = match error {
+            Error::RpcTransportError(e) => e.to_string(),
+            Error::Other(message) => message.clone(),
+            e => e.to_string(),
+        };
+        message.contains("insufficient funds")
+    }
+}
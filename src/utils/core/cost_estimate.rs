@@ -0,0 +1,122 @@
+use crate::utils::constants::{CALLDATA_NONZERO_BYTE_GAS, TX_BASE_GAS};
+use crate::utils::core::signed_bundle::BundleFees;
+use crate::utils::fees::calldata_gas;
+use crate::utils::network::NetworkConfig;
+use serde::Serialize;
+
+/// Expected and worst case cost, in wei, of a bundle transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BundleCost {
+    pub compressed_size: usize,
+    // calldata intrinsic gas, all a bundle tx uses
+    pub gas_used: u64,
+    pub fees: BundleFees,
+    pub base_fee_per_gas: u128,
+    // gas_used at the next block base fee plus the priority fee
+    pub expected_wei: u128,
+    // gas_limit * max_fee_per_gas, what the payer must hold
+    pub max_wei: u128,
+}
+
+impl BundleCost {
+    /// Prices `calldata` with the quoted fees, its gas limit following the
+    /// network fee policy.
+    pub fn new(
+        calldata: &[u8],
+        quote: &BundleFees,
+        base_fee_per_gas: u128,
+        network: &NetworkConfig,
+    ) -> Self {
+        Self::priced(
+            calldata.len(),
+            calldata_gas(calldata),
+            quote,
+            base_fee_per_gas,
+            network,
+        )
+    }
+
+    /// Worst case cost of a bundle whose calldata is `compressed_size` bytes
+    /// long, all of them priced as nonzero bytes. Nothing is signed nor
+    /// compressed.
+    pub fn worst_case(
+        compressed_size: usize,
+        quote: &BundleFees,
+        base_fee_per_gas: u128,
+        network: &NetworkConfig,
+    ) -> Self {
+        let gas_used = TX_BASE_GAS + compressed_size as u64 * CALLDATA_NONZERO_BYTE_GAS;
+        Self::priced(compressed_size, gas_used, quote, base_fee_per_gas, network)
+    }
+
+    fn priced(
+        compressed_size: usize,
+        gas_used: u64,
+        quote: &BundleFees,
+        base_fee_per_gas: u128,
+        network: &NetworkConfig,
+    ) -> Self {
+        let fees = BundleFees {
+            gas_limit: network.fees.gas_limit_for(gas_used, &network.gas),
+            ..*quote
+        };
+        let gas_price = base_fee_per_gas
+            .saturating_add(fees.max_priority_fee_per_gas)
+            .min(fees.max_fee_per_gas);
+
+        Self {
+            compressed_size,
+            gas_used,
+            fees,
+            base_fee_per_gas,
+            expected_wei: gas_used as u128 * gas_price,
+            max_wei: fees.gas_limit as u128 * fees.max_fee_per_gas,
+        }
+    }
+}
+
+/// Cost of a Large Bundle: its chunk bundles, then its manifest bundles.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LargeBundleCost {
+    pub chunks: Vec<BundleCost>,
+    // the manifest parts, if any, then the manifest
+    pub manifest: Vec<BundleCost>,
+    pub expected_wei: u128,
+    pub max_wei: u128,
+    pub chunkers_count: Option<usize>,
+    // worst case balance the busiest chunker needs for its chunks
+    pub per_chunker_wei: Option<u128>,
+}
+
+impl LargeBundleCost {
+    /// Chunks are spread evenly over the chunkers, when there are any.
+    pub fn new(
+        chunks: Vec<BundleCost>,
+        manifest: Vec<BundleCost>,
+        chunkers_count: Option<usize>,
+    ) -> Self {
+        let costs = || chunks.iter().chain(manifest.iter());
+        let per_chunker_wei = chunkers_count.filter(|count| *count > 0).map(|count| {
+            (0..count)
+                .map(|chunker| {
+                    chunks
+                        .iter()
+                        .skip(chunker)
+                        .step_by(count)
+                        .map(|cost| cost.max_wei)
+                        .sum::<u128>()
+                })
+                .max()
+                .unwrap_or_default()
+        });
+
+        Self {
+            expected_wei: costs().map(|cost| cost.expected_wei).sum(),
+            max_wei: costs().map(|cost| cost.max_wei).sum(),
+            chunks,
+            manifest,
+            chunkers_count,
+            per_chunker_wei,
+        }
+    }
+}
@@ -9,6 +9,12 @@ pub struct Envelope {
     pub tags: Option<Vec<Tag>>,
 }
 
+impl Default for Envelope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Envelope {
     pub fn new() -> Self {
         Self {
@@ -1,4 +1,3 @@
-use crate::utils::constants::CHAIN_ID;
 use crate::utils::core::envelope_signature::EnvelopeSignature;
 use crate::utils::core::tags::Tag;
 use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
@@ -43,9 +42,9 @@ pub struct BundleDataWithOwner {
 }
 
 impl TxEnvelopeWrapperWithOwner {
-    pub async fn from(envelope: TxEnvelopeWrapper) -> Self {
-        Self {
-            from: retrieve_envelope_owner(envelope.clone()).await.unwrap(),
+    pub async fn from(envelope: TxEnvelopeWrapper) -> Result<Self, Error> {
+        Ok(Self {
+            from: retrieve_envelope_owner(envelope.clone()).await?,
             chain_id: envelope.chain_id,
             nonce: envelope.nonce,
             gas_price: envelope.gas_price,
@@ -56,7 +55,7 @@ impl TxEnvelopeWrapperWithOwner {
             hash: envelope.hash,
             signature: envelope.signature,
             tags: envelope.tags,
-        }
+        })
     }
 }
 
@@ -67,9 +66,10 @@ impl BundleDataWithOwner {
 }
 
 pub async fn retrieve_envelope_owner(tx: TxEnvelopeWrapper) -> Result<String, Error> {
-    let tx = TxEnvelopeWrapper::to_tx_envelope(&tx).unwrap();
+    let chain_id = tx.chain_id;
+    let tx = TxEnvelopeWrapper::to_tx_envelope(&tx)?;
     let from = tx
         .recover_signer()
         .map_err(|_| Error::Other("Failed to parse to address".to_string()))?;
-    Ok(from.to_checksum(Some(CHAIN_ID)))
+    Ok(from.to_checksum(Some(chain_id)))
 }
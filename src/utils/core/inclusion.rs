@@ -0,0 +1,109 @@
+use crate::utils::errors::Error;
+use crate::utils::evm::HttpClient;
+use alloy::primitives::B256;
+use alloy::providers::Provider;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How long and how deep chunk transactions are waited for before a Large
+/// Bundle manifest is published.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionPolicy {
+    // blocks on top of, and including, the chunk's block; 0 skips the wait
+    pub confirmations: u64,
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    // re-submissions of a dropped or reverted chunk before giving up
+    pub max_resubmissions: usize,
+}
+
+impl Default for InclusionPolicy {
+    fn default() -> Self {
+        InclusionPolicy {
+            confirmations: 1,
+            timeout: Duration::from_secs(120),
+            poll_interval: Duration::from_secs(2),
+            max_resubmissions: 2,
+        }
+    }
+}
+
+impl InclusionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn max_resubmissions(mut self, max_resubmissions: usize) -> Self {
+        self.max_resubmissions = max_resubmissions;
+        self
+    }
+
+    /// Whether a transaction mined in `block_number` is deep enough at `latest`.
+    pub fn is_confirmed(&self, block_number: u64, latest: u64) -> bool {
+        latest.saturating_add(1).saturating_sub(block_number) >= self.confirmations
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inclusion {
+    // mined with a successful status and enough confirmations
+    Confirmed,
+    // mined but reverted
+    Failed,
+    // unknown to the node once the timeout elapsed
+    Dropped,
+    // still pending, or mined without enough confirmations, at the timeout
+    Pending,
+}
+
+/// Polls the receipt of `txid` until it is confirmed, reverted, or the policy
+/// timeout elapses.
+pub async fn wait_for_inclusion(
+    provider: &HttpClient,
+    txid: &str,
+    policy: &InclusionPolicy,
+) -> Result<Inclusion, Error> {
+    let hash = B256::from_str(&format!("0x{}", txid.trim_start_matches("0x")))?;
+    let deadline = Instant::now() + policy.timeout;
+
+    loop {
+        let mut seen = false;
+        if let Some(receipt) = provider.get_transaction_receipt(hash).await? {
+            if !receipt.status() {
+                return Ok(Inclusion::Failed);
+            }
+            if let Some(block_number) = receipt.block_number {
+                let latest = provider.get_block_number().await?;
+                if policy.is_confirmed(block_number, latest) {
+                    return Ok(Inclusion::Confirmed);
+                }
+            }
+            seen = true;
+        }
+
+        if Instant::now() >= deadline {
+            // a pending transaction must not be re-submitted next to itself
+            if seen || provider.get_transaction_by_hash(hash).await?.is_some() {
+                return Ok(Inclusion::Pending);
+            }
+            return Ok(Inclusion::Dropped);
+        }
+        tokio::time::sleep(policy.poll_interval).await;
+    }
+}
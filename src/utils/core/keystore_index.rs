@@ -0,0 +1,117 @@
+use crate::utils::errors::Error;
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const KEYSTORE_INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkerStatus {
+    Active,
+    // keystore moved under <keystore_path>/archive
+    Archived,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreEntry {
+    pub index: u32,
+    pub address: Address,
+    // relative to the keystore directory
+    pub file: String,
+    // unix seconds
+    pub created_at: u64,
+    pub status: ChunkerStatus,
+}
+
+impl KeystoreEntry {
+    pub fn new(index: u32, address: Address) -> Self {
+        Self {
+            index,
+            address,
+            file: Self::file_name(index),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            status: ChunkerStatus::Active,
+        }
+    }
+
+    pub fn file_name(index: u32) -> String {
+        format!("wallet_{}.json", index)
+    }
+}
+
+/// The chunkers of a SuperAccount keystore directory, in creation order.
+/// Indexes are never reused, so new chunkers can't overwrite retired ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreIndex {
+    pub chunkers: Vec<KeystoreEntry>,
+}
+
+impl KeystoreIndex {
+    pub fn path<P: AsRef<Path>>(keystore_dir: P) -> PathBuf {
+        keystore_dir.as_ref().join(KEYSTORE_INDEX_FILE)
+    }
+
+    /// Reads the index of `keystore_dir`, `None` if it has none yet.
+    pub fn load<P: AsRef<Path>>(keystore_dir: P) -> Result<Option<Self>, Error> {
+        let path = Self::path(keystore_dir);
+        let content = match std::fs::read(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::KeystoreIndex(format!("{}: {}", path.display(), e))),
+        };
+
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| Error::KeystoreIndex(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Writes the index through a temporary file, a crash never leaves it
+    /// half written.
+    pub fn save<P: AsRef<Path>>(&self, keystore_dir: P) -> Result<(), Error> {
+        let path = Self::path(&keystore_dir);
+        let tmp = path.with_extension("json.tmp");
+        let content =
+            serde_json::to_vec_pretty(self).map_err(|e| Error::KeystoreIndex(e.to_string()))?;
+
+        std::fs::write(&tmp, content)
+            .and_then(|_| std::fs::rename(&tmp, &path))
+            .map_err(|e| Error::KeystoreIndex(format!("{}: {}", path.display(), e)))
+    }
+
+    /// The index the next created chunker gets.
+    pub fn next_index(&self) -> u32 {
+        self.chunkers
+            .iter()
+            .map(|entry| entry.index + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The chunkers still in use, in creation order.
+    pub fn active(&self) -> impl Iterator<Item = &KeystoreEntry> {
+        self.chunkers
+            .iter()
+            .filter(|entry| entry.status == ChunkerStatus::Active)
+    }
+
+    pub fn push(&mut self, entry: KeystoreEntry) {
+        self.chunkers.push(entry);
+        self.chunkers.sort_by_key(|entry| entry.index);
+    }
+
+    pub fn set_status(&mut self, address: Address, status: ChunkerStatus) {
+        for entry in self
+            .chunkers
+            .iter_mut()
+            .filter(|entry| entry.address == address)
+        {
+            entry.status = status;
+        }
+    }
+}
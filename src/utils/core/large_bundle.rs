@@ -1,30 +1,78 @@
-use crate::utils::constants::WVM_RPC_URL;
 use crate::utils::constants::{
-    ADDRESS_BABE2, LB_CHUNK_MAX_SIZE, LB_SAFE_MAX_SIZE_LIMIT, MAX_SAFE_CHUNKS_IN_LB,
+    LB_CHUNK_BUNDLE_OVERHEAD, LB_CHUNK_MAX_SIZE, LB_DOWNLOAD_MAX_CONCURRENT, LB_MAX_SIZE_LIMIT,
+    LB_PROPAGATE_MAX_CONCURRENT, MAX_THEORETICAL_CHUNKS_IN_LB,
 };
 use crate::utils::core::bundle::Bundle;
 use crate::utils::core::bundle_tx_metadata::BundleTxMetadata;
+use crate::utils::core::chunk_dispatcher::ChunkDispatcher;
+use crate::utils::core::chunker_scheduler::SchedulerPolicy;
+use crate::utils::core::cost_estimate::{BundleCost, LargeBundleCost};
 use crate::utils::core::envelope::Envelope;
-use crate::utils::core::super_account::SuperAccount;
+use crate::utils::core::inclusion::{wait_for_inclusion, Inclusion, InclusionPolicy};
+use crate::utils::core::large_bundle_manifest::{
+    normalize_txid, parse_manifest, ChunkEntry, LargeBundleManifest, ManifestPart,
+    MANIFEST_PART_MAX_CHUNKS,
+};
+use crate::utils::core::large_bundle_source::LargeBundleSource;
+use crate::utils::core::super_account::{top_up_chunkers, SuperAccount};
 use crate::utils::core::tags::Tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::core::upload_journal::{SharedJournal, UploadJournal};
 use crate::utils::errors::Error;
 use crate::utils::evm::create_evm_http_client;
 use crate::utils::evm::{
-    create_bundle, create_bundle_sync, retrieve_bundle_data, retrieve_bundle_tx,
+    create_bundle, create_bundle_sync, create_bundle_sync_once, encode_bundle,
+    retrieve_bundle_data, retrieve_bundle_tx, sign_envelopes, HttpClient,
 };
-use futures::{self};
-use std::sync::Arc;
+use crate::utils::fees::next_base_fee;
+use crate::utils::network::NetworkConfig;
+use crate::utils::signer::BundlerSigner;
+use alloy::primitives::{keccak256, Address, Keccak256, U256};
+use bytes::Bytes;
+use futures::{self, Stream, StreamExt, TryStreamExt};
+use std::io::SeekFrom;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+/// Progress of a Large Bundle download, reported after each written chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub chunks_written: usize,
+    pub chunks_count: usize,
+    pub bytes_written: u64,
+    // known from a v2 manifest
+    pub total_size: Option<u64>,
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct LargeBundle {
     pub data: Option<Vec<u8>>,
     pub private_key: Option<String>,
+    // signs the chunks and manifest envelopes, defaults to the private key signer
+    pub signer: Option<BundlerSigner>,
+    // signs and pays for the chunks and manifest bundles, defaults to the signer
+    pub payer: Option<BundlerSigner>,
     pub owner_sig: Option<Vec<u8>>,
     pub chunks: Option<Vec<Vec<u8>>>,
     pub chunks_receipts: Option<Vec<String>>,
     pub content_type: Option<String>,
     pub super_account: Option<SuperAccount>,
     pub chunkers_count: Option<u32>,
+    pub filename: Option<String>,
+    // upload journal path, to resume interrupted uploads
+    pub journal: Option<String>,
+    // set once the chunks are propagated, or when read from a v2 manifest
+    pub manifest: Option<LargeBundleManifest>,
+    // chunks inclusion required before the manifest is published
+    pub inclusion: InclusionPolicy,
+    // how chunks are spread over the SuperAccount chunkers
+    pub scheduler: SchedulerPolicy,
+    // read and chunked while propagating, instead of `data`
+    pub source: Option<LargeBundleSource>,
+    // envelope signer of each propagated chunk, re-signing its re-submissions
+    pub chunk_signers: Option<Vec<Option<BundlerSigner>>>,
+    pub network: NetworkConfig,
 }
 
 impl LargeBundle {
@@ -32,20 +80,63 @@ impl LargeBundle {
         LargeBundle {
             data: None,
             private_key: None,
+            signer: None,
+            payer: None,
             owner_sig: None,
             chunks: None,
             chunks_receipts: None,
             content_type: None,
             super_account: None,
             chunkers_count: None,
+            filename: None,
+            journal: None,
+            manifest: None,
+            inclusion: InclusionPolicy::default(),
+            scheduler: SchedulerPolicy::default(),
+            source: None,
+            chunk_signers: None,
+            network: NetworkConfig::default(),
+        }
+    }
+
+    /// A Large Bundle whose data is read from `reader` chunk by chunk while
+    /// propagating, so it never has to fit in memory.
+    pub fn from_reader<R: AsyncRead + Send + Unpin + 'static>(reader: R) -> Self {
+        LargeBundle {
+            source: Some(LargeBundleSource::reader(reader)),
+            ..Self::new()
+        }
+    }
+
+    /// A Large Bundle whose data is read from the file at `path` chunk by
+    /// chunk while propagating.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        LargeBundle {
+            source: Some(LargeBundleSource::path(path.as_ref())),
+            ..Self::new()
         }
     }
 
+    pub fn network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
     pub fn private_key(mut self, key: String) -> Self {
         self.private_key = Some(key);
         self
     }
 
+    pub fn signer(mut self, signer: BundlerSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    pub fn payer(mut self, payer: BundlerSigner) -> Self {
+        self.payer = Some(payer);
+        self
+    }
+
     pub fn data(mut self, data: Vec<u8>) -> Self {
         self.data = Some(data);
         self
@@ -61,6 +152,26 @@ impl LargeBundle {
         self
     }
 
+    pub fn filename(mut self, filename: String) -> Self {
+        self.filename = Some(filename);
+        self
+    }
+
+    pub fn journal(mut self, path: String) -> Self {
+        self.journal = Some(path);
+        self
+    }
+
+    pub fn inclusion(mut self, policy: InclusionPolicy) -> Self {
+        self.inclusion = policy;
+        self
+    }
+
+    pub fn scheduler(mut self, policy: SchedulerPolicy) -> Self {
+        self.scheduler = policy;
+        self
+    }
+
     pub fn super_account(mut self, account: SuperAccount) -> Self {
         self.super_account = Some(account);
         self
@@ -77,323 +188,1343 @@ impl LargeBundle {
             .data
             .ok_or(Error::EnvelopesNeeded)
             .unwrap_or_default();
-        let data_len = data.len() as u32;
-
-        // data limits safety check: min 1 byte - max 2GB
-        assert!(data_len > 0 && data_len <= LB_SAFE_MAX_SIZE_LIMIT as u32);
+        let data_len = data.len() as u64;
 
-        // let chunks_count =
-        //     data_len / LB_CHUNK_MAX_SIZE + ((data_len % LB_CHUNK_MAX_SIZE) / LB_CHUNK_MAX_SIZE);
-        let chunks_count = (data_len + LB_CHUNK_MAX_SIZE - 1) / LB_CHUNK_MAX_SIZE;
+        // data limits safety check: min 1 byte - max ~241GB
+        assert!(data_len > 0 && data_len <= LB_MAX_SIZE_LIMIT);
 
-        let mut chunks = Vec::with_capacity(chunks_count as usize); // ensure at least 1 chunk is counted when data_len < LB_CHUNK_MAX_SIZE
-
-        for i in 0..chunks_count {
-            let start = (i * LB_CHUNK_MAX_SIZE) as usize;
-            let end = std::cmp::min((i + 1) * LB_CHUNK_MAX_SIZE, data_len) as usize;
-            let data_chunk = data[start..end].to_vec();
-            chunks.push(data_chunk);
-        }
+        // ensure at least 1 chunk is counted when data_len < LB_CHUNK_MAX_SIZE
+        let chunks: Vec<Vec<u8>> = data
+            .chunks(LB_CHUNK_MAX_SIZE as usize)
+            .map(|chunk| chunk.to_vec())
+            .collect();
 
         self.chunks = Some(chunks);
         self
     }
 
     pub fn build(self) -> Result<LargeBundle, Error> {
-        let data = self
-            .data
-            .filter(|e| !e.is_empty())
-            .ok_or(Error::EnvelopesNeeded)?;
-        let private_key = self
-            .private_key
-            .filter(|p| !p.is_empty())
-            .ok_or(Error::PrivateKeyNeeded)?;
-
-        let chunks = self
-            .chunks
-            .filter(|c| !c.is_empty())
-            .ok_or(Error::EnvelopesNeeded)?;
+        // streamed data is only chunked while propagating
+        let streamed = self.source.is_some();
+        let data = self.data.filter(|e| !e.is_empty());
+        if data.is_none() && !streamed {
+            return Err(Error::EnvelopesNeeded);
+        }
+        let private_key = self.private_key.filter(|p| !p.is_empty());
+        let signer = match self.signer {
+            Some(signer) => signer,
+            None => BundlerSigner::from_private_key(
+                private_key.as_deref().ok_or(Error::PrivateKeyNeeded)?,
+            )?,
+        };
+        let payer = self.payer.unwrap_or_else(|| signer.clone());
+
+        let chunks = self.chunks.filter(|c| !c.is_empty());
+        if chunks.is_none() && !streamed {
+            return Err(Error::EnvelopesNeeded);
+        }
 
         let content_type = self
             .content_type
             .unwrap_or("application/octet-stream".to_string());
 
         // additional check
-        assert!(chunks.len() as u32 <= MAX_SAFE_CHUNKS_IN_LB);
+        assert!(chunks.as_ref().map_or(0, |c| c.len()) <= MAX_THEORETICAL_CHUNKS_IN_LB as usize);
 
         let res = LargeBundle {
-            data: Some(data),
-            private_key: Some(private_key),
-            chunks: Some(chunks),
+            data,
+            private_key,
+            signer: Some(signer),
+            payer: Some(payer),
+            chunks,
             content_type: Some(content_type),
             chunks_receipts: self.chunks_receipts,
             owner_sig: self.owner_sig,
             super_account: self.super_account,
             chunkers_count: self.chunkers_count,
+            filename: self.filename,
+            journal: self.journal,
+            manifest: self.manifest,
+            inclusion: self.inclusion,
+            scheduler: self.scheduler,
+            source: self.source,
+            chunk_signers: self.chunk_signers,
+            network: self.network,
         };
 
         Ok(res)
     }
 
+    fn signers(&self) -> Result<(BundlerSigner, BundlerSigner), Error> {
+        let signer = self.signer.clone().ok_or(Error::PrivateKeyNeeded)?;
+        let payer = self.payer.clone().unwrap_or_else(|| signer.clone());
+        Ok((signer, payer))
+    }
+
     pub async fn propagate_chunks(mut self) -> Result<Self, Error> {
-        let mut chunks_index = 0;
+        // streamed chunks are signed by the signer too, the payer pays them
+        if let Some(source) = self.source.clone() {
+            let (signer, payer) = self.signers()?;
+            let dispatcher = self
+                .dispatcher(vec![payer], LB_PROPAGATE_MAX_CONCURRENT)
+                .await?
+                .signer(signer);
+            return self.dispatch_stream(source.open().await?, dispatcher).await;
+        }
+
         let chunks = self.clone().chunks.ok_or(Error::EnvelopesNeeded)?;
-        let mut chunks_receipts: Vec<String> = Vec::new();
-        let private_key: String = self.clone().private_key.ok_or(Error::PrivateKeyNeeded)?;
+        let mut hasher = Keccak256::new();
+        let chunk_size = chunks.first().map_or(0, |c| c.len() as u64);
+        let (signer, payer) = self.signers()?;
+        let journal = self.open_journal()?;
+        let http_client = create_evm_http_client(&self.network.rpc_url).await?;
 
-        for chunk in chunks {
-            let tags = vec![(Tag::new("chunk_index".to_string(), chunks_index.to_string()))];
-            let envelope = vec![Envelope::new().data(Some(chunk)).tags(Some(tags)).build()?];
-            let tx = create_bundle(None, envelope, private_key.clone(), ADDRESS_BABE2)
-                .await
-                .map_err(|_| Error::BundleNotCreated)?;
-            chunks_index += 1;
-            let chunk_hash = tx.tx_hash().to_string();
-            chunks_receipts.push(chunk_hash.clone().trim_start_matches("0x").to_string());
+        let confirmed: Vec<Option<ChunkEntry>> = chunks
+            .iter()
+            .enumerate()
+            .map(|(chunks_index, chunk)| {
+                hasher.update(chunk);
+                journal.as_ref().and_then(|journal| {
+                    journal.confirmed(chunks_index, &keccak256(chunk).to_string())
+                })
+            })
+            .collect();
+
+        // the nonce manager keeps the payer's concurrent broadcasts apart
+        let mut propagated = futures::stream::iter(chunks.iter().zip(confirmed).enumerate())
+            .map(|(chunks_index, (chunk, confirmed))| {
+                let (signer, payer, http_client) = (&signer, &payer, http_client.clone());
+                let network = &self.network;
+                async move {
+                    if let Some(entry) = confirmed {
+                        println!(
+                            "Chunk {} already propagated in {}",
+                            chunks_index, entry.txid
+                        );
+                        return Ok((chunks_index, entry, false));
+                    }
+
+                    let tags = vec![Tag::new(
+                        "chunk_index".to_string(),
+                        chunks_index.to_string(),
+                    )];
+                    let envelope = vec![Envelope::new()
+                        .data(Some(chunk.clone()))
+                        .tags(Some(tags))
+                        .build()?];
+                    let tx = create_bundle(
+                        Some(http_client),
+                        envelope,
+                        signer,
+                        payer,
+                        &network.babe2,
+                        network,
+                    )
+                    .await?;
+                    let entry = ChunkEntry::new(tx.tx_hash().to_string(), chunk);
+                    Ok::<_, Error>((chunks_index, entry, true))
+                }
+            })
+            .buffer_unordered(LB_PROPAGATE_MAX_CONCURRENT);
+
+        let mut entries: Vec<Option<ChunkEntry>> = vec![None; chunks.len()];
+        while let Some(result) = propagated.next().await {
+            let (chunks_index, entry, sent) = result?;
+            if let (true, Some(journal)) = (sent, journal.as_ref()) {
+                journal.record_chunk(chunks_index, entry.clone()).await?;
+            }
+            entries[chunks_index] = Some(entry);
         }
+        // releases the borrows of self
+        drop(propagated);
+
+        let entries: Vec<ChunkEntry> = entries.into_iter().flatten().collect();
+        let signers = vec![Some(signer.clone()); entries.len()];
+        self.set_manifest(
+            LargeBundleManifest::new(entries, chunk_size, hasher.finalize().to_string()),
+            signers,
+        );
+
+        Ok(self)
+    }
+
+    fn open_journal(&self) -> Result<Option<SharedJournal>, Error> {
+        let journal = self.journal.as_ref().map(UploadJournal::open).transpose()?;
+        Ok(journal.map(SharedJournal::new))
+    }
 
-        self.chunks_receipts = Some(chunks_receipts);
+    fn set_manifest(&mut self, manifest: LargeBundleManifest, signers: Vec<Option<BundlerSigner>>) {
+        self.chunk_signers = Some(signers);
+        self.chunks_receipts = Some(
+            manifest
+                .chunks
+                .iter()
+                .map(|c| normalize_txid(&c.txid))
+                .collect(),
+        );
+        self.manifest = Some(manifest);
+    }
+
+    /// Waits until every chunk transaction is mined with a successful status
+    /// and the inclusion policy confirmations. Dropped or reverted chunks are
+    /// re-submitted by the payer when the chunk data is at hand, in memory or
+    /// read back from the source file at the chunk's manifest offset. Their
+    /// envelope is signed again by the chunk's original signer.
+    pub async fn confirm_chunks(mut self) -> Result<Self, Error> {
+        let chunks_receipts = self.chunks_receipts.clone().ok_or(Error::EnvelopesNeeded)?;
+        if self.inclusion.confirmations == 0 {
+            return Ok(self);
+        }
+        let http_client = create_evm_http_client(&self.network.rpc_url).await?;
+        let signers = self.signers().ok();
+        let payer = signers.as_ref().map(|(_, payer)| payer);
+
+        let results: Vec<Result<Option<String>, Error>> =
+            futures::stream::iter(chunks_receipts.into_iter().enumerate())
+                .map(|(index, txid)| {
+                    let chunk = self.chunk_data(index);
+                    // chunks propagated elsewhere were signed by the signer
+                    let signer = match self.chunk_signers.as_ref() {
+                        Some(chunk_signers) => chunk_signers.get(index).cloned().flatten(),
+                        None => signers.as_ref().map(|(signer, _)| signer.clone()),
+                    };
+                    Self::confirm_chunk(
+                        index,
+                        txid,
+                        chunk,
+                        signer,
+                        payer,
+                        &http_client,
+                        &self.network,
+                        &self.inclusion,
+                    )
+                })
+                .buffered(16)
+                .collect()
+                .await;
+
+        let journal = self.open_journal()?;
+        for (index, result) in results.into_iter().enumerate() {
+            let Some(txid) = result? else {
+                continue;
+            };
+            if let Some(receipts) = self.chunks_receipts.as_mut() {
+                receipts[index] = txid.clone();
+            }
+            if let Some(entry) = self
+                .manifest
+                .as_mut()
+                .and_then(|manifest| manifest.chunks.get_mut(index))
+            {
+                entry.txid = normalize_txid(&txid);
+                if let Some(journal) = journal.as_ref() {
+                    journal.record_chunk(index, entry.clone()).await?;
+                }
+            }
+        }
 
         Ok(self)
     }
 
+    /// Where the chunk at `index` can be read back from, to re-submit it.
+    fn chunk_data(&self, index: usize) -> Option<ChunkData<'_>> {
+        if let Some(chunk) = self.chunks.as_ref().and_then(|chunks| chunks.get(index)) {
+            return Some(ChunkData::Memory(chunk));
+        }
+        let manifest = self.manifest.as_ref()?;
+        let entry = manifest.chunks.get(index)?;
+        Some(ChunkData::Source {
+            source: self.source.as_ref()?,
+            offset: manifest.chunk_offset(index),
+            entry,
+        })
+    }
+
+    /// Waits for a single chunk transaction, returns the replacement txid
+    /// when the chunk had to be re-submitted.
+    #[allow(clippy::too_many_arguments)]
+    async fn confirm_chunk(
+        chunk_index: usize,
+        mut txid: String,
+        chunk: Option<ChunkData<'_>>,
+        signer: Option<BundlerSigner>,
+        payer: Option<&BundlerSigner>,
+        http_client: &HttpClient,
+        network: &NetworkConfig,
+        policy: &InclusionPolicy,
+    ) -> Result<Option<String>, Error> {
+        let mut resubmissions = 0;
+        let mut replacement = None;
+
+        loop {
+            let outcome = match wait_for_inclusion(http_client, &txid, policy).await? {
+                Inclusion::Confirmed => return Ok(replacement),
+                Inclusion::Pending => {
+                    return Err(Error::ChunkNotIncluded {
+                        index: chunk_index,
+                        reason: format!("0x{} not confirmed within {:?}", txid, policy.timeout),
+                    })
+                }
+                Inclusion::Failed => "reverted",
+                Inclusion::Dropped => "dropped",
+            };
+
+            let (Some(chunk), Some(payer)) = (chunk.as_ref(), payer) else {
+                return Err(Error::ChunkNotIncluded {
+                    index: chunk_index,
+                    reason: format!("0x{} was {} and the chunk is not available", txid, outcome),
+                });
+            };
+            let Some(signer) = signer.as_ref() else {
+                return Err(Error::ChunkNotIncluded {
+                    index: chunk_index,
+                    reason: format!("0x{} was {} and its signer is not known", txid, outcome),
+                });
+            };
+            if resubmissions == policy.max_resubmissions {
+                return Err(Error::ChunkNotIncluded {
+                    index: chunk_index,
+                    reason: format!(
+                        "0x{} was {} after {} re-submissions",
+                        txid, outcome, resubmissions
+                    ),
+                });
+            }
+
+            println!(
+                "Chunk {} was {} in 0x{}, re-submitting",
+                chunk_index, outcome, txid
+            );
+            let chunk = chunk
+                .load(chunk_index)
+                .await?
+                .ok_or(Error::ChunkNotIncluded {
+                    index: chunk_index,
+                    reason: format!("0x{} was {} and the chunk is not available", txid, outcome),
+                })?;
+            txid = Self::propagate_chunk(
+                chunk_index,
+                chunk,
+                signer,
+                payer,
+                http_client.clone(),
+                network,
+            )
+            .await?;
+            replacement = Some(txid.clone());
+            resubmissions += 1;
+        }
+    }
+
     pub async fn finalize(self) -> Result<String, Error> {
-        let private_key: String = self.clone().private_key.ok_or(Error::PrivateKeyNeeded)?;
-        let chunks_receipts = self.chunks_receipts.ok_or(Error::EnvelopesNeeded)?;
-        let http_client = create_evm_http_client(WVM_RPC_URL)
+        // the manifest must only reference mined chunks
+        let bundle = self.confirm_chunks().await?;
+        let (signer, payer) = bundle.signers()?;
+        let journal = bundle.open_journal()?;
+        let chunks_receipts = bundle.chunks_receipts.ok_or(Error::EnvelopesNeeded)?;
+        let content_type = bundle
+            .content_type
+            .unwrap_or("application/octet-stream".to_string());
+        let http_client = create_evm_http_client(&bundle.network.rpc_url)
             .await
             .map_err(|err| Error::Other(err.to_string()))?;
 
+        // v2 manifest when the chunks were hashed while propagated, otherwise
         // Vec<String> -> stringified Vec<String> (String) -> &[u8]-> Vec<u8>
-        let data = serde_json::to_string(&chunks_receipts)
-            .map_err(|e| Error::Other(e.to_string()))?
-            .as_bytes()
-            .to_vec();
+        let mut manifest_version = None;
+        let data = match bundle.manifest {
+            Some(mut manifest) => {
+                manifest.normalize_txids();
+                // a single manifest envelope would outgrow the bundle size limit
+                if manifest.chunks.len() > MANIFEST_PART_MAX_CHUNKS {
+                    let parts = Self::propagate_manifest_parts(
+                        &manifest.chunks,
+                        &signer,
+                        &payer,
+                        &http_client,
+                        &bundle.network,
+                        &bundle.inclusion,
+                    )
+                    .await?;
+                    manifest = manifest.into_hierarchical(parts);
+                }
+                let manifest = manifest
+                    .content_type(Some(content_type.clone()))
+                    .filename(bundle.filename);
+                manifest_version = Some(manifest.version);
+                serde_json::to_vec(&manifest)
+            }
+            None => serde_json::to_vec(
+                &chunks_receipts
+                    .iter()
+                    .map(|txid| normalize_txid(txid))
+                    .collect::<Vec<String>>(),
+            ),
+        }
+        .map_err(|e| Error::Other(e.to_string()))?;
+
+        let tags = Self::manifest_tags(chunks_receipts.len(), &content_type, manifest_version);
+        let receipts_envelope = vec![Envelope::new().data(Some(data)).tags(Some(tags)).build()?];
+
+        let tx = create_bundle_sync(
+            Some(http_client),
+            receipts_envelope,
+            &signer,
+            &payer,
+            &bundle.network.babe2,
+            &bundle.network,
+        )
+        .await?;
+
+        let large_bundle_txid = tx.tx_hash().to_string();
+        if let Some(journal) = journal.as_ref() {
+            journal.record_finalized(large_bundle_txid.clone()).await?;
+        }
+
+        Ok(large_bundle_txid)
+    }
+
+    /// Estimates what propagating and finalizing this built Large Bundle
+    /// costs at the current fees, without sending anything. Each chunk bundle
+    /// is signed and compressed as it would be propagated.
+    pub async fn estimate_cost(&self) -> Result<LargeBundleCost, Error> {
+        let chunks = self.chunks.as_ref().ok_or(Error::EnvelopesNeeded)?;
+        let (signer, _) = self.signers()?;
+        let network = &self.network;
+        let http_client = create_evm_http_client(&network.rpc_url).await?;
+        let quote = network.fees.estimate_fees(&http_client, &[], network).await;
+        let base_fee = next_base_fee(&http_client).await?;
+        let price = |calldata: &[u8]| BundleCost::new(calldata, &quote, base_fee, network);
+
+        let mut chunks_costs = Vec::with_capacity(chunks.len());
+        let mut entries = Vec::with_capacity(chunks.len());
+        let mut hasher = Keccak256::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let tags = vec![Tag::new("chunk_index".to_string(), index.to_string())];
+            let envelope = Envelope::new()
+                .data(Some(chunk.clone()))
+                .tags(Some(tags))
+                .build()?;
+            chunks_costs.push(price(
+                &Self::compressed_bundle(envelope, &signer, network).await?,
+            ));
+            // the chunk txids are unknown yet, any hash stands for them
+            entries.push(ChunkEntry::new(
+                keccak256(index.to_be_bytes()).to_string(),
+                chunk,
+            ));
+            hasher.update(chunk);
+        }
+
+        let chunk_size = chunks.first().map_or(0, |c| c.len() as u64);
+        let mut manifest =
+            LargeBundleManifest::new(entries, chunk_size, hasher.finalize().to_string());
+        let mut manifest_costs = Vec::new();
+        if manifest.chunks.len() > MANIFEST_PART_MAX_CHUNKS {
+            let mut parts = Vec::new();
+            for (part_index, part_chunks) in
+                manifest.chunks.chunks(MANIFEST_PART_MAX_CHUNKS).enumerate()
+            {
+                let data =
+                    serde_json::to_vec(part_chunks).map_err(|e| Error::Other(e.to_string()))?;
+                let envelope = Envelope::new()
+                    .data(Some(data.clone()))
+                    .tags(Some(Self::manifest_part_tags(part_index)))
+                    .build()?;
+                manifest_costs.push(price(
+                    &Self::compressed_bundle(envelope, &signer, network).await?,
+                ));
+                parts.push(ManifestPart::new(
+                    keccak256(&data).to_string(),
+                    part_index * MANIFEST_PART_MAX_CHUNKS,
+                    part_chunks.len(),
+                    &data,
+                ));
+            }
+            manifest = manifest.into_hierarchical(parts);
+        }
+
+        let content_type = self
+            .content_type
+            .clone()
+            .unwrap_or("application/octet-stream".to_string());
+        let manifest = manifest
+            .content_type(Some(content_type.clone()))
+            .filename(self.filename.clone());
+        let tags = Self::manifest_tags(chunks.len(), &content_type, Some(manifest.version));
+        let data = serde_json::to_vec(&manifest).map_err(|e| Error::Other(e.to_string()))?;
+        let envelope = Envelope::new().data(Some(data)).tags(Some(tags)).build()?;
+        manifest_costs.push(price(
+            &Self::compressed_bundle(envelope, &signer, network).await?,
+        ));
+
+        // the chunkers share the chunks with a SuperAccount
+        let chunkers_count = self.super_account.as_ref().and_then(|account| {
+            self.chunkers_count
+                .map(|count| count as usize)
+                .or(account.chunkers.as_ref().map(Vec::len))
+        });
+
+        Ok(LargeBundleCost::new(
+            chunks_costs,
+            manifest_costs,
+            chunkers_count,
+        ))
+    }
+
+    /// A single envelope bundle, compressed as `create_bundle_sync` does, which
+    /// is at worst slightly larger than `create_bundle`'s.
+    async fn compressed_bundle(
+        envelope: Envelope,
+        signer: &BundlerSigner,
+        network: &NetworkConfig,
+    ) -> Result<Vec<u8>, Error> {
+        let envelopes = sign_envelopes(vec![envelope], signer, network).await?;
+        Ok(encode_bundle(envelopes, network, true)?.compressed)
+    }
 
-        let tags: Vec<Tag> = vec![
+    fn manifest_tags(
+        chunks_count: usize,
+        content_type: &str,
+        manifest_version: Option<u8>,
+    ) -> Vec<Tag> {
+        let mut tags = vec![
             Tag::new("Protocol".to_string(), "Large-Bundle".to_string()),
+            Tag::new("Chunks-Count".to_string(), chunks_count.to_string()),
+            Tag::new("Content-Type".to_string(), "application/json".to_string()),
+            Tag::new("Data-Content-Type".to_string(), content_type.to_string()),
+        ];
+        if let Some(version) = manifest_version {
+            tags.push(Tag::new(
+                "Manifest-Version".to_string(),
+                version.to_string(),
+            ));
+        }
+        tags
+    }
+
+    fn manifest_part_tags(part_index: usize) -> Vec<Tag> {
+        vec![
             Tag::new(
-                "Chunks-Count".to_string(),
-                chunks_receipts.len().to_string(),
+                "Protocol".to_string(),
+                "Large-Bundle-Manifest-Part".to_string(),
             ),
+            Tag::new("Part-Index".to_string(), part_index.to_string()),
             Tag::new("Content-Type".to_string(), "application/json".to_string()),
-            Tag::new("Data-Content-Type".to_string(), self.content_type.unwrap()),
-        ];
+        ]
+    }
 
-        let receipts_envelope = vec![Envelope::new().data(Some(data)).tags(Some(tags)).build()?];
+    /// Stores the chunk entries in `MANIFEST_PART_MAX_CHUNKS` long parts and
+    /// waits for their inclusion, returning the parts of a v3 manifest.
+    async fn propagate_manifest_parts(
+        chunks: &[ChunkEntry],
+        signer: &BundlerSigner,
+        payer: &BundlerSigner,
+        http_client: &HttpClient,
+        network: &NetworkConfig,
+        policy: &InclusionPolicy,
+    ) -> Result<Vec<ManifestPart>, Error> {
+        let mut parts = Vec::new();
+        for (part_index, part_chunks) in chunks.chunks(MANIFEST_PART_MAX_CHUNKS).enumerate() {
+            let data = serde_json::to_vec(part_chunks).map_err(|e| Error::Other(e.to_string()))?;
+            let envelope = vec![Envelope::new()
+                .data(Some(data.clone()))
+                .tags(Some(Self::manifest_part_tags(part_index)))
+                .build()?];
+            let tx = create_bundle_sync(
+                Some(http_client.clone()),
+                envelope,
+                signer,
+                payer,
+                &network.babe2,
+                network,
+            )
+            .await?;
 
-        let tx = create_bundle_sync(
+            parts.push(ManifestPart::new(
+                tx.tx_hash().to_string(),
+                part_index * MANIFEST_PART_MAX_CHUNKS,
+                part_chunks.len(),
+                &data,
+            ));
+        }
+
+        if policy.confirmations > 0 {
+            futures::future::try_join_all(parts.iter_mut().enumerate().map(|(index, part)| {
+                Self::confirm_manifest_part(
+                    index,
+                    part,
+                    &chunks[part.first_chunk..part.first_chunk + part.chunks_count],
+                    signer,
+                    payer,
+                    http_client,
+                    network,
+                    policy,
+                )
+            }))
+            .await?;
+        }
+
+        Ok(parts)
+    }
+
+    /// Waits for a manifest part transaction, re-submitting the part when it
+    /// was dropped or reverted.
+    #[allow(clippy::too_many_arguments)]
+    async fn confirm_manifest_part(
+        part_index: usize,
+        part: &mut ManifestPart,
+        part_chunks: &[ChunkEntry],
+        signer: &BundlerSigner,
+        payer: &BundlerSigner,
+        http_client: &HttpClient,
+        network: &NetworkConfig,
+        policy: &InclusionPolicy,
+    ) -> Result<(), Error> {
+        let mut resubmissions = 0;
+
+        loop {
+            let outcome = match wait_for_inclusion(http_client, &part.txid, policy).await? {
+                Inclusion::Confirmed => return Ok(()),
+                Inclusion::Pending => {
+                    return Err(Error::ManifestPartNotIncluded {
+                        index: part_index,
+                        reason: format!("{} not confirmed within {:?}", part.txid, policy.timeout),
+                    })
+                }
+                Inclusion::Failed => "reverted",
+                Inclusion::Dropped => "dropped",
+            };
+            if resubmissions == policy.max_resubmissions {
+                return Err(Error::ManifestPartNotIncluded {
+                    index: part_index,
+                    reason: format!(
+                        "{} was {} after {} re-submissions",
+                        part.txid, outcome, resubmissions
+                    ),
+                });
+            }
+
+            println!(
+                "Manifest part {} was {} in {}, re-submitting",
+                part_index, outcome, part.txid
+            );
+            let data = serde_json::to_vec(part_chunks).map_err(|e| Error::Other(e.to_string()))?;
+            let envelope = vec![Envelope::new()
+                .data(Some(data))
+                .tags(Some(Self::manifest_part_tags(part_index)))
+                .build()?];
+            let tx = create_bundle_sync(
+                Some(http_client.clone()),
+                envelope,
+                signer,
+                payer,
+                &network.babe2,
+                network,
+            )
+            .await?;
+            part.txid = normalize_txid(&tx.tx_hash().to_string());
+            resubmissions += 1;
+        }
+    }
+
+    /// Resumes an interrupted upload of this built Large Bundle: the chunks
+    /// recorded in the journal are skipped, the missing ones are propagated,
+    /// then the Large Bundle is finalized, unless the journal says it was.
+    pub async fn resume<P: AsRef<Path>>(mut self, journal: P) -> Result<String, Error> {
+        let journal = journal.as_ref().to_string_lossy().to_string();
+        if let Some(large_bundle_txid) = UploadJournal::open(&journal)?.finalized() {
+            return Ok(large_bundle_txid.clone());
+        }
+        self.journal = Some(journal);
+
+        let large_bundle = if self.super_account.is_some() {
+            self.super_propagate_chunks().await?
+        } else {
+            self.propagate_chunks().await?
+        };
+        large_bundle.finalize().await
+    }
+
+    /// Propagates a single tagged chunk bundle, retrying the broadcast on failure.
+    pub async fn propagate_chunk(
+        chunk_index: usize,
+        chunk: Vec<u8>,
+        signer: &BundlerSigner,
+        payer: &BundlerSigner,
+        http_client: HttpClient,
+        network: &NetworkConfig,
+    ) -> Result<String, Error> {
+        // multiple retry attempts for robustness
+        const MAX_RETRIES: usize = 3;
+        let mut last_error = None;
+
+        for attempt in 1..=MAX_RETRIES {
+            match Self::propagate_chunk_once(
+                chunk_index,
+                chunk.clone(),
+                signer,
+                payer,
+                http_client.clone(),
+                network,
+            )
+            .await
+            {
+                Ok(txid) => return Ok(txid),
+                Err(e) if attempt < MAX_RETRIES => {
+                    last_error = Some(e);
+                    // Short backoff before retry
+                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                }
+                Err(e) => {
+                    last_error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(Error::BundleNotCreated))
+    }
+
+    /// Sends a chunk once, its envelope signed by `signer` and its bundle paid
+    /// by `payer`, returning its txid without the 0x prefix.
+    pub async fn propagate_chunk_once(
+        chunk_index: usize,
+        chunk: Vec<u8>,
+        signer: &BundlerSigner,
+        payer: &BundlerSigner,
+        http_client: HttpClient,
+        network: &NetworkConfig,
+    ) -> Result<String, Error> {
+        let tags = vec![(Tag::new("chunk_index".to_string(), chunk_index.to_string()))];
+        let envelope = vec![Envelope::new().data(Some(chunk)).tags(Some(tags)).build()?];
+
+        let tx_result = create_bundle_sync_once(
             Some(http_client),
-            receipts_envelope,
-            private_key,
-            ADDRESS_BABE2,
+            envelope,
+            signer,
+            payer,
+            &network.babe2,
+            network,
         )
-        .await
-        .map_err(|_| Error::BundleNotCreated)?;
+        .await?;
 
-        Ok(tx.tx_hash().to_string())
+        Ok(tx_result
+            .tx_hash()
+            .to_string()
+            .trim_start_matches("0x")
+            .to_string())
     }
 
     pub async fn retrieve_chunks_receipts(bundle_txid: String) -> Result<LargeBundle, Error> {
-        let bundle: BundleTxMetadata = retrieve_bundle_tx(bundle_txid)
-            .await
-            .map_err(|_| Error::BundleRetrievalProblem)?;
+        Self::retrieve_chunks_receipts_with_network(bundle_txid, &NetworkConfig::default()).await
+    }
+
+    pub async fn retrieve_chunks_receipts_with_network(
+        bundle_txid: String,
+        network: &NetworkConfig,
+    ) -> Result<LargeBundle, Error> {
+        let bundle: BundleTxMetadata = retrieve_bundle_tx(bundle_txid, network).await?;
         // assert the bundle versioning by checking target address
-        if bundle.to.to_lowercase() != ADDRESS_BABE2.to_string().to_ascii_lowercase() {
+        if bundle.to.to_lowercase() != network.babe2.to_ascii_lowercase() {
             return Err(Error::UnverifiedAddress);
         }
 
-        let large_bundle = retrieve_bundle_data(bundle.calldata).await;
+        let large_bundle = retrieve_bundle_data(bundle.calldata).await?;
         let chunks_receipts = large_bundle
             .envelopes
-            .get(0)
+            .first()
             .ok_or_else(|| Error::LargeBundleChunksRetrieval)?;
 
         // retrieve Large Bundle Data-Content-Type
         let data_content_type = chunks_receipts
             .clone()
             .tags
-            .unwrap()
+            .unwrap_or_default()
             .iter()
             .find(|tag| tag.name.to_lowercase() == "data-content-type")
             .map(|tag| tag.value.clone())
             .unwrap_or_default();
 
-        // retrieve Large Bundle chunk receipts
-        let receipts_data = hex::decode(&chunks_receipts.input.trim_start_matches("0x"))
+        // retrieve Large Bundle chunk receipts, v1 array or v2 manifest
+        let receipts_data = hex::decode(chunks_receipts.input.trim_start_matches("0x"))
             .map_err(|e| Error::Other(e.to_string()))?;
-        let chunks_receipts: Vec<String> = serde_json::from_str(
-            &String::from_utf8(receipts_data).map_err(|e| Error::Other(e.to_string()))?,
-        )
-        .map_err(|e| Error::Other(e.to_string()))?;
-
-        let chunks_receipts_with_prefix: Vec<String> = chunks_receipts
-            .into_iter()
-            .map(|chunk| format!("0x{}", chunk))
-            .collect();
+        let (mut chunks_receipts, mut manifest) = parse_manifest(&receipts_data)?;
+        if let Some(hierarchical) = manifest.take_if(|m| m.is_hierarchical()) {
+            let hierarchical = Self::retrieve_manifest_parts(hierarchical, network).await?;
+            chunks_receipts = hierarchical.txids();
+            manifest = Some(hierarchical);
+        }
 
         Ok(Self {
-            chunks_receipts: Some(chunks_receipts_with_prefix),
+            chunks_receipts: Some(chunks_receipts),
             content_type: Some(data_content_type),
+            filename: manifest.as_ref().and_then(|m| m.filename.clone()),
+            manifest,
+            network: network.clone(),
             ..Default::default()
         })
     }
 
+    /// Retrieves the parts of a v3 manifest, filling its chunk entries.
+    async fn retrieve_manifest_parts(
+        manifest: LargeBundleManifest,
+        network: &NetworkConfig,
+    ) -> Result<LargeBundleManifest, Error> {
+        let parts_chunks: Vec<Vec<ChunkEntry>> = futures::stream::iter(manifest.parts.clone())
+            .map(|part| Self::retrieve_manifest_part(part, network))
+            .buffered(LB_DOWNLOAD_MAX_CONCURRENT)
+            .try_collect()
+            .await?;
+
+        manifest.with_parts_chunks(parts_chunks)
+    }
+
+    async fn retrieve_manifest_part(
+        part: ManifestPart,
+        network: &NetworkConfig,
+    ) -> Result<Vec<ChunkEntry>, Error> {
+        let bundle =
+            Bundle::retrieve_envelopes_with_network(part.txid.clone(), &network.babe2, network)
+                .await?;
+        let envelope = bundle
+            .envelopes
+            .first()
+            .ok_or(Error::LargeBundleChunksRetrieval)?;
+        let data = hex::decode(envelope.input.trim_start_matches("0x"))
+            .map_err(|e| Error::Other(e.to_string()))?;
+        part.parse_chunks(&data)
+    }
+
     pub async fn reconstruct_large_bundle(self) -> Result<Vec<u8>, Error> {
-        let chunks_receipts = self
-            .chunks_receipts
-            .ok_or_else(|| Error::LargeBundleChunksRetrieval)?;
+        let mut data = Vec::new();
+        self.download_to(&mut data, LB_DOWNLOAD_MAX_CONCURRENT, |_| {})
+            .await?;
+        Ok(data)
+    }
 
-        let receipt_futures = chunks_receipts
-            .clone()
-            .into_iter()
-            .map(|receipt| async move {
-                let receipt_bundle = Bundle::retrieve_envelopes(receipt.clone(), ADDRESS_BABE2)
-                    .await
-                    .map_err(|_| Error::LargeBundleReconstruction)?;
-                let receipt_writer = receipt_bundle
-                    .envelopes
-                    .get(0)
-                    .ok_or_else(|| Error::Other("Error: no envelopes found".to_string()))?;
-                let receipt_data = hex::decode(&receipt_writer.input.trim_start_matches("0x"))
-                    .map_err(|e| Error::Other(e.to_string()))?;
-                Ok::<Vec<u8>, Error>(receipt_data)
-            });
+    /// Retrieves the chunk at `index`, checking its `chunk_index` tag and,
+    /// when a v2 manifest is known, its size and hash. Retrieval failures are
+    /// retried, integrity failures are not.
+    pub async fn retrieve_chunk(
+        index: usize,
+        receipt: &str,
+        manifest: Option<&LargeBundleManifest>,
+        network: &NetworkConfig,
+    ) -> Result<Vec<u8>, Error> {
+        const MAX_RETRIES: usize = 3;
+        let mut attempt = 1;
 
-        let results = futures::future::try_join_all(receipt_futures)
+        let receipt_bundle = loop {
+            match Bundle::retrieve_envelopes_with_network(
+                receipt.to_string(),
+                &network.babe2,
+                network,
+            )
             .await
-            .map_err(|e| Error::Other(e.to_string()))?;
-        Ok(results.into_iter().flatten().collect::<Vec<u8>>())
+            {
+                Ok(bundle) => break bundle,
+                // only transport failures may go away, a bad bundle stays bad
+                Err(e @ Error::RpcTransportError(_)) if attempt < MAX_RETRIES => {
+                    println!("Retrieving chunk {} failed ({}), retrying", index, e);
+                    tokio::time::sleep(tokio::time::Duration::from_millis(200 * attempt as u64))
+                        .await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    println!("Retrieving chunk {} failed ({}), giving up", index, e);
+                    return Err(e);
+                }
+            }
+        };
+
+        let chunk = receipt_bundle
+            .envelopes
+            .first()
+            .ok_or_else(|| Error::ChunkIntegrity {
+                index,
+                reason: "no envelopes found".to_string(),
+            })?;
+
+        Self::verify_chunk(index, chunk, manifest)
+    }
+
+    /// Checks a chunk envelope against its position and the manifest entry,
+    /// returning the chunk data.
+    pub fn verify_chunk(
+        index: usize,
+        chunk: &TxEnvelopeWrapper,
+        manifest: Option<&LargeBundleManifest>,
+    ) -> Result<Vec<u8>, Error> {
+        let integrity_error = |reason: String| Error::ChunkIntegrity { index, reason };
+
+        let chunk_index = chunk
+            .tags
+            .as_ref()
+            .and_then(|tags| tags.iter().find(|tag| tag.name == "chunk_index"))
+            .ok_or_else(|| integrity_error("missing chunk_index tag".to_string()))?;
+        if chunk_index.value != index.to_string() {
+            return Err(integrity_error(format!(
+                "chunk_index tag is {}",
+                chunk_index.value
+            )));
+        }
+
+        let data = hex::decode(chunk.input.trim_start_matches("0x"))
+            .map_err(|e| integrity_error(e.to_string()))?;
+
+        if let Some(entry) = manifest.and_then(|m| m.chunks.get(index)) {
+            if data.len() as u64 != entry.size {
+                return Err(integrity_error(format!(
+                    "size is {}, the manifest records {}",
+                    data.len(),
+                    entry.size
+                )));
+            }
+            let hash = keccak256(&data).to_string();
+            if !hash.eq_ignore_ascii_case(&entry.hash) {
+                return Err(integrity_error(format!(
+                    "hash is {}, the manifest records {}",
+                    hash, entry.hash
+                )));
+            }
+        }
+
+        Ok(data)
     }
 }
 
 // SuperAccount method
 impl LargeBundle {
     pub async fn super_propagate_chunks(mut self) -> Result<Self, Error> {
-        let chunks = self
-            .clone()
-            .chunks
-            .ok_or(Error::LargeBundleChunksRetrieval)?;
+        let chunks = self.chunks.clone();
+        if chunks.is_none() && self.source.is_none() {
+            return Err(Error::LargeBundleChunksRetrieval);
+        }
         let super_account = self
             .clone()
             .super_account
             .ok_or(Error::SuperAccountNeeded)?;
         let chunkers_count = self.clone().chunkers_count;
 
-        let chunkers = super_account
+        let super_account_keystore = super_account.keystore_path.clone();
+        let super_account = super_account
             .load_chunkers(chunkers_count) // Load all available chunkers
-            .await?
+            .await?;
+        let chunkers = super_account
             .chunkers
+            .clone()
             .ok_or(Error::ChunkersNeeded)?;
+        self.super_account = Some(super_account.clone());
+
+        // a funder tops up the chunkers that can't afford their chunks
+        if super_account.funder.is_some() {
+            let funder = super_account.funder_signer()?;
+            let addresses: Vec<Address> =
+                chunkers.iter().map(|chunker| chunker.address()).collect();
+            self.top_up_chunkers(&funder, &addresses, self.size().await)
+                .await?;
+        }
 
         let chunkers_count = chunkers.len();
+        let chunkers: Vec<BundlerSigner> = chunkers.into_iter().map(BundlerSigner::new).collect();
+        let max_concurrent = std::cmp::min(chunkers_count, self.scheduler.max_concurrent);
+
+        // journal next to the keystores by default, named after the data
+        if self.journal.is_none() {
+            if let (Some(keystore_path), Some(key)) =
+                (super_account_keystore.as_ref(), self.journal_key().await)
+            {
+                let path = Path::new(keystore_path).join(format!("{}.journal", key));
+                self.journal = Some(path.to_string_lossy().to_string());
+            }
+        }
+
+        let Some(chunks) = chunks else {
+            println!("Processing streamed data with {} chunkers", chunkers_count);
+            let stream = self
+                .source
+                .clone()
+                .ok_or(Error::EnvelopesNeeded)?
+                .open()
+                .await?;
+            return self
+                .propagate_stream(stream, chunkers, max_concurrent)
+                .await;
+        };
         println!(
             "Processing {} chunks with {} chunkers",
             chunks.len(),
             chunkers_count
         );
-        let mut chunks_receipts: Vec<Option<String>> = vec![None; chunks.len()];
-        let chunkers = Arc::new(chunkers);
+        let mut dispatcher = self.dispatcher(chunkers, max_concurrent).await?;
+        for chunk in chunks {
+            dispatcher.dispatch(chunk).await?;
+        }
 
-        let http_client = create_evm_http_client(WVM_RPC_URL)
-            .await
-            .map_err(|err| Error::Other(err.to_string()))?;
-        let max_concurrent = std::cmp::min(chunkers_count, 30);
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrent));
-        let (tx, mut rx) =
-            tokio::sync::mpsc::channel::<Result<(usize, String), Error>>(chunks.len());
-
-        for (chunk_index, chunk) in chunks.clone().into_iter().enumerate() {
-            let chunkers = Arc::clone(&chunkers);
-            let semaphore = Arc::clone(&semaphore);
-            let http_client = http_client.clone();
-            let tx = tx.clone();
-
-            tokio::spawn(async move {
-                let _permit = semaphore.acquire().await.unwrap();
-
-                // Determine which chunker to use (round-robin)
-                let chunker_index = chunk_index % chunkers_count;
-                let chunker = &chunkers[chunker_index];
-                let tags = vec![(Tag::new("chunk_index".to_string(), chunk_index.to_string()))];
-                let envelope = match Envelope::new().data(Some(chunk)).tags(Some(tags)).build() {
-                    Ok(env) => {
-                        vec![env]
-                    }
-                    Err(e) => {
-                        println!("Task {} failed to create envelope: {}", chunk_index, e);
-                        return;
-                    }
-                };
-
-                // multiple retry attempts for robustness
-                const MAX_RETRIES: usize = 3;
-                let mut last_error = None;
-
-                for attempt in 1..=MAX_RETRIES {
-                    match create_bundle_sync(
-                        Some(http_client.clone()),
-                        envelope.clone(),
-                        chunker.to_bytes().to_string(),
-                        ADDRESS_BABE2,
-                    )
-                    .await
-                    {
-                        Ok(tx_result) => {
-                            let chunk_hash = tx_result
-                                .tx_hash()
-                                .to_string()
-                                .trim_start_matches("0x")
-                                .to_string();
-                            let _ = tx.send(Ok((chunk_index, chunk_hash))).await;
-                            return;
-                        }
-                        Err(e) if attempt < MAX_RETRIES => {
-                            last_error = Some(e);
-                            // Short backoff before retry
-                            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                        }
-                        Err(e) => {
-                            last_error = Some(e);
-                            break;
-                        }
-                    }
-                }
+        let (manifest, signers) = dispatcher.finish().await?;
+        self.set_manifest(manifest, signers);
 
-                let error = last_error.unwrap_or(Error::BundleNotCreated);
-                let _ = tx.send(Err(error)).await;
-            });
+        Ok(self)
+    }
+
+    /// Tops up, from `funder`, the `chunkers` whose balance is below the worst
+    /// case cost of their share of the chunks, plus room for the fee bumps of
+    /// the network fee policy. The cost is derived from the data size, so
+    /// nothing is signed nor compressed. Sources of unknown size, such as a
+    /// reader, are not topped up.
+    pub async fn top_up_chunkers(
+        &self,
+        funder: &BundlerSigner,
+        chunkers: &[Address],
+        size: Option<u64>,
+    ) -> Result<(), Error> {
+        let Some(size) = size else {
+            println!("Large Bundle size unknown, chunkers are not topped up");
+            return Ok(());
+        };
+        let required = self.chunker_funding(size, chunkers.len()).await?;
+        let target = required.saturating_mul(100 + self.network.fees.bump_percent as u128) / 100;
+
+        let topped_up = top_up_chunkers(
+            funder,
+            chunkers,
+            U256::from(required),
+            U256::from(target),
+            &self.network,
+        )
+        .await?;
+        if !topped_up.is_empty() {
+            println!("Topped up {} chunkers", topped_up.len());
         }
 
-        // drop the original sender so the channel can close when all tasks complete
-        drop(tx);
+        Ok(())
+    }
 
-        // process results as they arrive
-        let mut received_count = 0;
+    /// Worst case balance the busiest of `chunkers_count` chunkers needs to
+    /// propagate its share of `size` bytes of data, at the current fees.
+    pub async fn chunker_funding(&self, size: u64, chunkers_count: usize) -> Result<u128, Error> {
+        let network = &self.network;
+        let http_client = create_evm_http_client(&network.rpc_url).await?;
+        let quote = network.fees.estimate_fees(&http_client, &[], network).await;
 
-        while let Some(result) = rx.recv().await {
-            match result {
-                Ok((index, hash)) => {
-                    chunks_receipts[index] = Some(hash);
-                    received_count += 1;
-                }
-                Err(e) => {
-                    println!("Error processing chunk: {}", e);
-                    return Err(e);
+        // the base fee only weighs on the expected cost, not on max_wei
+        let chunk_size = size.min(LB_CHUNK_MAX_SIZE as u64);
+        let chunk = BundleCost::worst_case(
+            (chunk_size + LB_CHUNK_BUNDLE_OVERHEAD) as usize,
+            &quote,
+            0,
+            network,
+        );
+        let chunks_count = size.div_ceil(LB_CHUNK_MAX_SIZE as u64).max(1);
+        let per_chunker = chunks_count.div_ceil(chunkers_count.max(1) as u64);
+
+        Ok(chunk.max_wei.saturating_mul(per_chunker as u128))
+    }
+
+    /// Names the default upload journal after the data: its hash when it is
+    /// in memory, the path and size of a path source. A reader has no name,
+    /// a journal must then be set explicitly.
+    async fn journal_key(&self) -> Option<String> {
+        if let Some(data) = self.data.as_ref() {
+            return Some(keccak256(data).to_string());
+        }
+        let Some(LargeBundleSource::Path(path)) = self.source.as_ref() else {
+            return None;
+        };
+        let path = tokio::fs::canonicalize(path).await.ok()?;
+        let size = tokio::fs::metadata(&path).await.ok()?.len();
+        Some(keccak256(format!("{}:{}", path.display(), size)).to_string())
+    }
+
+    /// The data size in bytes, when it is known before propagating.
+    pub async fn size(&self) -> Option<u64> {
+        if let Some(data) = self.data.as_ref() {
+            return Some(data.len() as u64);
+        }
+        if let Some(chunks) = self.chunks.as_ref() {
+            return Some(chunks.iter().map(|chunk| chunk.len() as u64).sum());
+        }
+        self.source.as_ref()?.size().await
+    }
+
+    /// Chunks a byte stream into `LB_CHUNK_MAX_SIZE` pieces as it arrives and
+    /// propagates them through the chunkers, holding at most `max_concurrent`
+    /// chunks in memory. Each chunker signs the envelopes of its chunks.
+    /// `finalize` can then be called on the result.
+    pub async fn propagate_stream<S, E>(
+        self,
+        stream: S,
+        chunkers: Vec<BundlerSigner>,
+        max_concurrent: usize,
+    ) -> Result<Self, Error>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let dispatcher = self.dispatcher(chunkers, max_concurrent).await?;
+        self.dispatch_stream(stream, dispatcher).await
+    }
+
+    /// A dispatcher over `chunkers`, recording in the journal when one is set.
+    async fn dispatcher(
+        &self,
+        chunkers: Vec<BundlerSigner>,
+        max_concurrent: usize,
+    ) -> Result<ChunkDispatcher, Error> {
+        let mut dispatcher = ChunkDispatcher::new(
+            chunkers,
+            self.network.clone(),
+            self.scheduler.clone().max_concurrent(max_concurrent),
+        )
+        .await?;
+        if let Some(journal) = self.open_journal()? {
+            dispatcher = dispatcher.journal(journal);
+        }
+        Ok(dispatcher)
+    }
+
+    async fn dispatch_stream<S, E>(
+        mut self,
+        mut stream: S,
+        mut dispatcher: ChunkDispatcher,
+    ) -> Result<Self, Error>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let chunk_size = LB_CHUNK_MAX_SIZE as usize;
+        let mut buffer: Vec<u8> = Vec::with_capacity(chunk_size);
+        let mut total_size: u64 = 0;
+
+        while let Some(frame) = stream.next().await {
+            let mut frame = frame.map_err(|e| Error::Other(e.to_string()))?;
+            total_size += frame.len() as u64;
+            // data limits safety check: max ~241GB
+            if total_size > LB_MAX_SIZE_LIMIT {
+                return Err(Error::LargeBundleTooLarge(LB_MAX_SIZE_LIMIT));
+            }
+
+            while !frame.is_empty() {
+                let take = std::cmp::min(chunk_size - buffer.len(), frame.len());
+                buffer.extend_from_slice(&frame.split_to(take));
+
+                if buffer.len() == chunk_size {
+                    let chunk = std::mem::replace(&mut buffer, Vec::with_capacity(chunk_size));
+                    dispatcher.dispatch(chunk).await?;
                 }
             }
         }
 
-        let chunks_receipts = chunks_receipts
-            .into_iter()
-            .enumerate()
-            .map(|(i, hash)| {
-                hash.ok_or_else(|| Error::Other(format!("Missing receipt for chunk {}", i)))
-            })
-            .collect::<Result<Vec<String>, Error>>()?;
+        if !buffer.is_empty() {
+            dispatcher.dispatch(buffer).await?;
+        }
+        if dispatcher.dispatched() == 0 {
+            return Err(Error::EnvelopesNeeded);
+        }
 
-        self.chunks_receipts = Some(chunks_receipts);
+        let (manifest, signers) = dispatcher.finish().await?;
+        self.set_manifest(manifest, signers);
 
         Ok(self)
     }
 }
+
+// download methods
+impl LargeBundle {
+    /// Writes the Large Bundle data to `writer` in chunks order, retrieving at
+    /// most `max_concurrent` chunks at once. `progress` is called after each
+    /// written chunk. Returns the data size.
+    pub async fn download_to<W, F>(
+        &self,
+        writer: W,
+        max_concurrent: usize,
+        progress: F,
+    ) -> Result<u64, Error>
+    where
+        W: AsyncWrite + Unpin,
+        F: FnMut(DownloadProgress),
+    {
+        self.download_from(writer, 0, 0, Keccak256::new(), max_concurrent, progress)
+            .await
+    }
+
+    /// Downloads the Large Bundle data into the file at `path`. The chunks a
+    /// previous partial download already wrote are kept, as long as they
+    /// match the manifest, and the download resumes after them. v1 Large
+    /// Bundles have no chunk hashes to check, so their download restarts.
+    pub async fn download_to_path<P, F>(
+        &self,
+        path: P,
+        max_concurrent: usize,
+        progress: F,
+    ) -> Result<u64, Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(DownloadProgress),
+    {
+        let io_error = |e: std::io::Error| Error::Other(e.to_string());
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(path)
+            .await
+            .map_err(io_error)?;
+
+        let (first_chunk, offset, hasher) = self.written_prefix(&mut file).await?;
+        file.set_len(offset).await.map_err(io_error)?;
+        file.seek(SeekFrom::Start(offset)).await.map_err(io_error)?;
+
+        let size = self
+            .download_from(
+                &mut file,
+                first_chunk,
+                offset,
+                hasher,
+                max_concurrent,
+                progress,
+            )
+            .await?;
+        file.sync_all().await.map_err(io_error)?;
+
+        Ok(size)
+    }
+
+    /// The chunks count and bytes already written to `file` that match their
+    /// manifest entry, with the hasher fed with them. Without a manifest,
+    /// nothing written can be verified.
+    async fn written_prefix(&self, file: &mut File) -> Result<(usize, u64, Keccak256), Error> {
+        let io_error = |e: std::io::Error| Error::Other(e.to_string());
+        let chunks_count = self
+            .chunks_receipts
+            .as_ref()
+            .ok_or(Error::LargeBundleChunksRetrieval)?
+            .len();
+        let written = file.metadata().await.map_err(io_error)?.len();
+        let mut hasher = Keccak256::new();
+        let mut offset = 0;
+        let Some(manifest) = self.manifest.as_ref() else {
+            return Ok((0, offset, hasher));
+        };
+
+        for index in 0..chunks_count {
+            let Some(entry) = manifest.chunks.get(index) else {
+                return Ok((index, offset, hasher));
+            };
+            if offset + entry.size > written {
+                return Ok((index, offset, hasher));
+            }
+
+            let mut chunk = vec![0; entry.size as usize];
+            file.read_exact(&mut chunk).await.map_err(io_error)?;
+            if !keccak256(&chunk)
+                .to_string()
+                .eq_ignore_ascii_case(&entry.hash)
+            {
+                return Ok((index, offset, hasher));
+            }
+            hasher.update(&chunk);
+            offset += entry.size;
+        }
+
+        Ok((chunks_count, offset, hasher))
+    }
+
+    async fn download_from<W, F>(
+        &self,
+        mut writer: W,
+        first_chunk: usize,
+        mut bytes_written: u64,
+        mut hasher: Keccak256,
+        max_concurrent: usize,
+        mut progress: F,
+    ) -> Result<u64, Error>
+    where
+        W: AsyncWrite + Unpin,
+        F: FnMut(DownloadProgress),
+    {
+        let io_error = |e: std::io::Error| Error::Other(e.to_string());
+        let chunks_receipts = self
+            .chunks_receipts
+            .as_ref()
+            .ok_or(Error::LargeBundleChunksRetrieval)?;
+        let manifest = self.manifest.as_ref();
+        let network = &self.network;
+
+        // in order, with at most max_concurrent chunks retrieved or held
+        let mut chunks =
+            futures::stream::iter(chunks_receipts.iter().enumerate().skip(first_chunk))
+                .map(|(index, receipt)| Self::retrieve_chunk(index, receipt, manifest, network))
+                .buffered(max_concurrent.max(1));
+
+        let mut chunks_written = first_chunk;
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            writer.write_all(&chunk).await.map_err(io_error)?;
+            hasher.update(&chunk);
+            chunks_written += 1;
+            bytes_written += chunk.len() as u64;
+            progress(DownloadProgress {
+                chunks_written,
+                chunks_count: chunks_receipts.len(),
+                bytes_written,
+                total_size: manifest.map(|manifest| manifest.total_size),
+            });
+        }
+        writer.flush().await.map_err(io_error)?;
+
+        if let Some(manifest) = manifest {
+            let hash = hasher.finalize().to_string();
+            if !hash.eq_ignore_ascii_case(&manifest.hash) {
+                return Err(Error::LargeBundleHashMismatch {
+                    expected: manifest.hash.clone(),
+                    actual: hash,
+                });
+            }
+        }
+
+        Ok(bytes_written)
+    }
+}
+
+// a chunk to re-submit, held in memory or read back from a path source
+enum ChunkData<'a> {
+    Memory(&'a [u8]),
+    Source {
+        source: &'a LargeBundleSource,
+        offset: u64,
+        entry: &'a ChunkEntry,
+    },
+}
+
+impl ChunkData<'_> {
+    /// The chunk bytes, `None` when the source can't be read again. Bytes read
+    /// back must still match the chunk hash recorded while propagating.
+    async fn load(&self, index: usize) -> Result<Option<Vec<u8>>, Error> {
+        let (source, offset, entry) = match self {
+            ChunkData::Memory(chunk) => return Ok(Some(chunk.to_vec())),
+            ChunkData::Source {
+                source,
+                offset,
+                entry,
+            } => (source, *offset, entry),
+        };
+        let Some(chunk) = source.read_at(offset, entry.size).await? else {
+            return Ok(None);
+        };
+
+        let actual = keccak256(&chunk).to_string();
+        if actual != entry.hash {
+            return Err(Error::ChunkIntegrity {
+                index,
+                reason: format!(
+                    "source changed since propagation, hash {} instead of {}",
+                    actual, entry.hash
+                ),
+            });
+        }
+        Ok(Some(chunk))
+    }
+}
@@ -0,0 +1,215 @@
+use crate::utils::errors::Error;
+use alloy::primitives::keccak256;
+use serde::{Deserialize, Serialize};
+
+pub const MANIFEST_VERSION: u8 = 2;
+// chunks listed in manifest parts instead of the root manifest
+pub const HIERARCHICAL_MANIFEST_VERSION: u8 = 3;
+// ~170 bytes per JSON chunk entry, far below the bundle size limit
+pub const MANIFEST_PART_MAX_CHUNKS: usize = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    pub txid: String,
+    pub size: u64,
+    // keccak256 of the chunk data
+    pub hash: String,
+}
+
+impl ChunkEntry {
+    pub fn new(txid: String, chunk: &[u8]) -> Self {
+        Self {
+            txid: normalize_txid(&txid),
+            size: chunk.len() as u64,
+            hash: keccak256(chunk).to_string(),
+        }
+    }
+}
+
+/// A slice of the chunk entries stored in its own envelope, as a JSON array,
+/// and referenced by a v3 root manifest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ManifestPart {
+    pub txid: String,
+    pub first_chunk: usize,
+    pub chunks_count: usize,
+    // keccak256 of the part's JSON data
+    pub hash: String,
+}
+
+impl ManifestPart {
+    pub fn new(txid: String, first_chunk: usize, chunks_count: usize, data: &[u8]) -> Self {
+        Self {
+            txid: normalize_txid(&txid),
+            first_chunk,
+            chunks_count,
+            hash: keccak256(data).to_string(),
+        }
+    }
+
+    /// Parses the part's data, checking it against the part hash and count.
+    pub fn parse_chunks(&self, data: &[u8]) -> Result<Vec<ChunkEntry>, Error> {
+        let hash = keccak256(data).to_string();
+        if !hash.eq_ignore_ascii_case(&self.hash) {
+            return Err(Error::ManifestIntegrity(format!(
+                "manifest part {} hash {} does not match {}",
+                self.txid, hash, self.hash
+            )));
+        }
+        let mut chunks: Vec<ChunkEntry> =
+            serde_json::from_slice(data).map_err(|_| Error::LargeBundleChunksRetrieval)?;
+        if chunks.len() != self.chunks_count {
+            return Err(Error::ManifestIntegrity(format!(
+                "manifest part {} lists {} chunks instead of {}",
+                self.txid,
+                chunks.len(),
+                self.chunks_count
+            )));
+        }
+        for chunk in chunks.iter_mut() {
+            chunk.txid = normalize_txid(&chunk.txid);
+        }
+        Ok(chunks)
+    }
+}
+
+/// Large Bundle manifest stored by `finalize`, replacing the v1 JSON array
+/// of chunk txids.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LargeBundleManifest {
+    pub version: u8,
+    pub total_size: u64,
+    // size of every chunk but the last one
+    pub chunk_size: u64,
+    // empty in a stored v3 manifest until its parts are retrieved
+    pub chunks: Vec<ChunkEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<ManifestPart>,
+    // keccak256 of the whole data
+    pub hash: String,
+    pub content_type: Option<String>,
+    pub filename: Option<String>,
+}
+
+impl LargeBundleManifest {
+    pub fn new(chunks: Vec<ChunkEntry>, chunk_size: u64, hash: String) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            total_size: chunks.iter().map(|c| c.size).sum(),
+            chunk_size,
+            chunks,
+            parts: Vec::new(),
+            hash,
+            content_type: None,
+            filename: None,
+        }
+    }
+
+    pub fn content_type(mut self, content_type: Option<String>) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    pub fn filename(mut self, filename: Option<String>) -> Self {
+        self.filename = filename;
+        self
+    }
+
+    pub fn is_hierarchical(&self) -> bool {
+        self.version == HIERARCHICAL_MANIFEST_VERSION
+    }
+
+    /// The manifest to store once its chunk entries are stored in `parts`.
+    pub fn into_hierarchical(self, parts: Vec<ManifestPart>) -> Self {
+        Self {
+            version: HIERARCHICAL_MANIFEST_VERSION,
+            chunks: Vec::new(),
+            parts,
+            ..self
+        }
+    }
+
+    /// Fills the chunk entries of a v3 manifest from its parts, in order.
+    pub fn with_parts_chunks(mut self, parts_chunks: Vec<Vec<ChunkEntry>>) -> Result<Self, Error> {
+        let mut chunks = Vec::new();
+        for (part, part_chunks) in self.parts.iter().zip(parts_chunks) {
+            if part.first_chunk != chunks.len() {
+                return Err(Error::ManifestIntegrity(format!(
+                    "manifest part {} starts at chunk {} instead of {}",
+                    part.txid,
+                    part.first_chunk,
+                    chunks.len()
+                )));
+            }
+            chunks.extend(part_chunks);
+        }
+        let total_size: u64 = chunks.iter().map(|c| c.size).sum();
+        if total_size != self.total_size {
+            return Err(Error::ManifestIntegrity(format!(
+                "manifest parts sum to {} bytes instead of {}",
+                total_size, self.total_size
+            )));
+        }
+        self.chunks = chunks;
+        Ok(self)
+    }
+
+    /// Strips the 0x prefix from the chunk and part txids.
+    pub fn normalize_txids(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            chunk.txid = normalize_txid(&chunk.txid);
+        }
+        for part in self.parts.iter_mut() {
+            part.txid = normalize_txid(&part.txid);
+        }
+    }
+
+    pub fn txids(&self) -> Vec<String> {
+        self.chunks.iter().map(|c| c.txid.clone()).collect()
+    }
+
+    /// Byte offset of the chunk at `index` in the whole data.
+    pub fn chunk_offset(&self, index: usize) -> u64 {
+        self.chunks.iter().take(index).map(|c| c.size).sum()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ManifestFormat {
+    Manifest(LargeBundleManifest),
+    // v1: chunk txids without the 0x prefix, as every manifest version
+    Receipts(Vec<String>),
+}
+
+/// Reads both the v1 txids array and the v2 and v3 manifests, returning the
+/// unprefixed chunk txids and the manifest when there is one. The txids of
+/// a v3 manifest are only known once its parts are retrieved.
+pub fn parse_manifest(data: &[u8]) -> Result<(Vec<String>, Option<LargeBundleManifest>), Error> {
+    let format: ManifestFormat =
+        serde_json::from_slice(data).map_err(|_| Error::LargeBundleChunksRetrieval)?;
+
+    match format {
+        ManifestFormat::Receipts(receipts) => Ok((
+            receipts.iter().map(|txid| normalize_txid(txid)).collect(),
+            None,
+        )),
+        ManifestFormat::Manifest(mut manifest) => {
+            if manifest.version != MANIFEST_VERSION
+                && manifest.version != HIERARCHICAL_MANIFEST_VERSION
+            {
+                return Err(Error::ManifestIntegrity(format!(
+                    "unsupported Large Bundle manifest version {}",
+                    manifest.version
+                )));
+            }
+            manifest.normalize_txids();
+            Ok((manifest.txids(), Some(manifest)))
+        }
+    }
+}
+
+/// Txids are stored and compared without the 0x prefix, as in v1 manifests.
+pub fn normalize_txid(txid: &str) -> String {
+    txid.trim_start_matches("0x").to_string()
+}
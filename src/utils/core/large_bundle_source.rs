@@ -0,0 +1,91 @@
+use crate::utils::errors::Error;
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+use std::fmt;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub type SourceReader = Box<dyn AsyncRead + Send + Unpin>;
+pub type SourceStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+/// Where a Large Bundle reads its data from when it is not held in memory.
+#[derive(Clone)]
+pub enum LargeBundleSource {
+    Path(PathBuf),
+    // taken by the first propagation, a reader can't be read twice
+    Reader(Arc<Mutex<Option<SourceReader>>>),
+}
+
+impl LargeBundleSource {
+    pub fn path<P: Into<PathBuf>>(path: P) -> Self {
+        LargeBundleSource::Path(path.into())
+    }
+
+    pub fn reader<R: AsyncRead + Send + Unpin + 'static>(reader: R) -> Self {
+        LargeBundleSource::Reader(Arc::new(Mutex::new(Some(Box::new(reader)))))
+    }
+
+    /// The source size in bytes, unknown for a reader.
+    pub async fn size(&self) -> Option<u64> {
+        match self {
+            LargeBundleSource::Path(path) => tokio::fs::metadata(path).await.ok().map(|m| m.len()),
+            LargeBundleSource::Reader(_) => None,
+        }
+    }
+
+    /// Reads `size` bytes at `offset`, to re-submit a chunk. `None` for a
+    /// reader, which can't be read again.
+    pub async fn read_at(&self, offset: u64, size: u64) -> Result<Option<Vec<u8>>, Error> {
+        let LargeBundleSource::Path(path) = self else {
+            return Ok(None);
+        };
+        let io_error = |e: std::io::Error| Error::Other(format!("{}: {}", path.display(), e));
+
+        let mut file = tokio::fs::File::open(path).await.map_err(io_error)?;
+        file.seek(SeekFrom::Start(offset)).await.map_err(io_error)?;
+        let mut chunk = vec![0; size as usize];
+        file.read_exact(&mut chunk).await.map_err(io_error)?;
+        Ok(Some(chunk))
+    }
+
+    /// Opens the source as a stream of frames of at most 64KB.
+    pub async fn open(&self) -> Result<SourceStream, Error> {
+        let reader: SourceReader = match self {
+            LargeBundleSource::Path(path) => Box::new(
+                tokio::fs::File::open(path)
+                    .await
+                    .map_err(|e| Error::Other(format!("{}: {}", path.display(), e)))?,
+            ),
+            LargeBundleSource::Reader(reader) => reader
+                .lock()
+                .map_err(|e| Error::Other(e.to_string()))?
+                .take()
+                .ok_or(Error::Other(
+                    "Large Bundle reader was already consumed".to_string(),
+                ))?,
+        };
+
+        Ok(Box::pin(futures::stream::try_unfold(
+            reader,
+            |mut reader| async move {
+                let mut frame = BytesMut::with_capacity(READ_BUFFER_SIZE);
+                let read = reader.read_buf(&mut frame).await?;
+                Ok((read > 0).then(|| (frame.freeze(), reader)))
+            },
+        )))
+    }
+}
+
+impl fmt::Debug for LargeBundleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LargeBundleSource::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            LargeBundleSource::Reader(_) => f.debug_tuple("Reader").finish(),
+        }
+    }
+}
@@ -1,10 +1,21 @@
 pub mod bundle;
 pub mod bundle_data;
 pub mod bundle_tx_metadata;
+pub mod bundle_validator;
+pub mod chunk_dispatcher;
+pub mod chunker_scheduler;
+pub mod cost_estimate;
 pub mod envelope;
 pub mod envelope_signature;
 pub mod envelope_with_owner;
+pub mod inclusion;
+pub mod keystore_index;
 pub mod large_bundle;
+pub mod large_bundle_manifest;
+pub mod large_bundle_source;
+pub mod signed_bundle;
 pub mod super_account;
 pub mod tags;
 pub mod tx_envelope_writer;
+pub mod upload_journal;
+pub mod verified_bundle;
@@ -0,0 +1,40 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::network::GasPolicy;
+use serde::{Deserialize, Serialize};
+
+/// EIP-1559 fee fields of a bundle (outer) transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BundleFees {
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl From<&GasPolicy> for BundleFees {
+    fn from(gas: &GasPolicy) -> Self {
+        Self {
+            gas_limit: gas.bundle_gas_limit,
+            max_fee_per_gas: gas.max_fee_per_gas,
+            max_priority_fee_per_gas: gas.max_priority_fee_per_gas,
+        }
+    }
+}
+
+/// Signed envelopes of a bundle, Borsh serialized and Brotli compressed.
+#[derive(Debug, Default, PartialEq)]
+pub struct EncodedBundle {
+    pub bundle: BundleData,
+    pub serialized: Vec<u8>,
+    pub compressed: Vec<u8>,
+}
+
+/// An encoded bundle wrapped in a signed EIP-1559 transaction, ready to be
+/// submitted with `Bundle::submit_raw`.
+#[derive(Debug, PartialEq)]
+pub struct SignedBundle {
+    pub encoded: EncodedBundle,
+    pub nonce: u64,
+    pub fees: BundleFees,
+    pub tx_hash: String,
+    pub raw_tx: Vec<u8>,
+}
@@ -1,28 +1,104 @@
-use crate::utils::constants::SAFE_CHUNK_TOPUP;
+use crate::utils::constants::{
+    HD_CHUNKERS_COUNT, HD_DERIVATION_PATH, SAFE_CHUNK_TOPUP, TX_BASE_GAS,
+};
+use crate::utils::core::keystore_index::{ChunkerStatus, KeystoreEntry, KeystoreIndex};
 use crate::utils::errors::Error;
-use crate::utils::evm::{send_wvm, AlloyPk};
-use alloy::primitives::{Address, B256};
+use crate::utils::evm::{create_evm_http_client, transfer_wvm, AlloyPk};
+use crate::utils::network::NetworkConfig;
+use crate::utils::signer::BundlerSigner;
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::Provider;
 use alloy::signers::k256::Secp256k1;
-use alloy::signers::local::LocalSigner;
+use alloy::signers::local::coins_bip39::English;
+use alloy::signers::local::{LocalSigner, MnemonicBuilder};
 use ecdsa::SigningKey;
 use eyre::OptionExt;
-use futures::future::join_all;
+use futures::future::{join_all, try_join_all};
 use rand::{thread_rng, RngCore};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::future::IntoFuture;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
 use tokio::task;
 
 pub type Chunker = LocalSigner<SigningKey<Secp256k1>>;
 
-#[derive(Debug, Clone, Default)]
+/// A chunker's balance, in wei, and the nonces of its next mined and next
+/// sent txs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkerBalance {
+    pub address: Address,
+    pub balance: U256,
+    pub latest_nonce: u64,
+    pub pending_nonce: u64,
+}
+
+impl ChunkerBalance {
+    /// Txs sent by the chunker that are still waiting in the mempool.
+    pub fn pending_txs(&self) -> u64 {
+        self.pending_nonce.saturating_sub(self.latest_nonce)
+    }
+}
+
+/// What happens to the keystore files of the chunkers drained by a sweep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeystoreRetention {
+    #[default]
+    Keep,
+    // moved under <keystore_path>/archive
+    Archive,
+    Delete,
+}
+
+impl KeystoreRetention {
+    fn status(self) -> Option<ChunkerStatus> {
+        match self {
+            KeystoreRetention::Keep => None,
+            KeystoreRetention::Archive => Some(ChunkerStatus::Archived),
+            KeystoreRetention::Delete => Some(ChunkerStatus::Deleted),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct SuperAccount {
     pub funder: Option<AlloyPk>,
     pub keystore_path: Option<String>,
     pub keystore_pwd: Option<String>,
     pub chunkers: Option<Vec<Chunker>>,
+    // keystore file of each loaded chunker
+    pub keystores: HashMap<Address, PathBuf>,
+    // BIP-39 phrase the chunkers are derived from, instead of the keystore
+    pub mnemonic: Option<String>,
+    pub derivation_path: Option<String>,
+    pub derivation_range: Option<Range<u32>>,
+    pub network: NetworkConfig,
+}
+
+// the funder key, keystore password and mnemonic are redacted
+impl fmt::Debug for SuperAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |secret: bool| secret.then_some("<redacted>");
+        let chunkers: Option<Vec<Address>> = self
+            .chunkers
+            .as_ref()
+            .map(|chunkers| chunkers.iter().map(|chunker| chunker.address()).collect());
+        f.debug_struct("SuperAccount")
+            .field("funder", &redacted(self.funder.is_some()))
+            .field("keystore_path", &self.keystore_path)
+            .field("keystore_pwd", &redacted(self.keystore_pwd.is_some()))
+            .field("chunkers", &chunkers)
+            .field("keystores", &self.keystores)
+            .field("mnemonic", &redacted(self.mnemonic.is_some()))
+            .field("derivation_path", &self.derivation_path)
+            .field("derivation_range", &self.derivation_range)
+            .field("network", &self.network)
+            .finish()
+    }
 }
 
 impl SuperAccount {
@@ -49,121 +125,432 @@ impl SuperAccount {
         self
     }
 
-    pub async fn fund_chunkers(mut self) -> Result<Self, Error> {
+    pub fn network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Derives the chunkers from a BIP-39 `phrase` rather than loading them
+    /// from keystore files.
+    pub fn mnemonic(mut self, phrase: String) -> Self {
+        self.mnemonic = Some(phrase);
+        self
+    }
+
+    /// Chunker `i` is derived at `<path>/i`, `HD_DERIVATION_PATH` by default.
+    pub fn derivation_path(mut self, path: String) -> Self {
+        self.derivation_path = Some(path.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Indexes of the derived chunkers, `0..HD_CHUNKERS_COUNT` by default.
+    pub fn derivation_range(mut self, range: Range<u32>) -> Self {
+        self.derivation_range = Some(range);
+        self
+    }
+
+    pub async fn fund_chunkers(self) -> Result<Self, Error> {
         let chunkers = self
             .chunkers
             .clone()
             .ok_or_eyre("Error: chunkers not found")?;
-        let funder = self.funder.ok_or_else(|| Error::PrivateKeyNeeded)?;
+        let funder = self.funder_signer()?;
 
-        for chunker in chunkers {
-            send_wvm(funder, chunker.address(), SAFE_CHUNK_TOPUP).await?;
-        }
+        try_join_all(chunkers.iter().map(|chunker| {
+            self.transfer(&funder, chunker.address(), U256::from(SAFE_CHUNK_TOPUP))
+        }))
+        .await?;
 
         Ok(self)
     }
 
-    pub async fn create_chunkers(self, amount: u32) -> Result<Self, Error> {
-        let path = self
-            .clone()
-            .keystore_path
-            .ok_or_else(|| Error::InvalidKeystore)?;
-        let pwd = self
-            .clone()
-            .keystore_pwd
-            .ok_or_else(|| Error::InvalidKeystore)?;
+    /// Balance and pending nonce of every loaded chunker, in chunkers order.
+    pub async fn balances(&self) -> Result<Vec<ChunkerBalance>, Error> {
+        let chunkers = self.chunkers.as_ref().ok_or(Error::ChunkersNeeded)?;
+        let provider = create_evm_http_client(&self.network.rpc_url).await?;
+
+        try_join_all(chunkers.iter().map(|chunker| {
+            let provider = &provider;
+            async move {
+                let address = chunker.address();
+                let (balance, latest_nonce, pending_nonce) = tokio::try_join!(
+                    provider.get_balance(address),
+                    provider.get_transaction_count(address).latest(),
+                    provider.get_transaction_count(address).pending()
+                )?;
+
+                Ok::<_, Error>(ChunkerBalance {
+                    address,
+                    balance,
+                    latest_nonce,
+                    pending_nonce,
+                })
+            }
+        }))
+        .await
+    }
+
+    /// Funds the chunkers holding less than `min_balance` up to
+    /// `target_balance`, concurrently. Returns the top up txs hashes.
+    pub async fn top_up(
+        &self,
+        min_balance: U256,
+        target_balance: U256,
+    ) -> Result<Vec<String>, Error> {
+        let funder = self.funder_signer()?;
+        let chunkers = self.chunkers.as_ref().ok_or(Error::ChunkersNeeded)?;
+        let addresses: Vec<Address> = chunkers.iter().map(|chunker| chunker.address()).collect();
+
+        top_up_chunkers(
+            &funder,
+            &addresses,
+            min_balance,
+            target_balance,
+            &self.network,
+        )
+        .await
+    }
 
-        let keystore_dir = Path::new(&path);
-        std::fs::create_dir_all(keystore_dir).map_err(|err| Error::Other(err.to_string()))?;
+    /// Drains every loaded chunker back to `to`, the funder by default. Each
+    /// chunker sends its whole balance minus the exact fee of a plain
+    /// transfer, and chunkers left empty have their keystore retired as
+    /// `retention` says. Returns the sweep txs hashes.
+    pub async fn sweep(
+        &self,
+        to: Option<Address>,
+        retention: KeystoreRetention,
+    ) -> Result<Vec<String>, Error> {
+        let to = match to {
+            Some(to) => to,
+            None => self.funder_signer()?.address(),
+        };
+        let chunkers = self.chunkers.as_ref().ok_or(Error::ChunkersNeeded)?;
+        let balances = self.balances().await?;
+        let fee = U256::from(TX_BASE_GAS) * U256::from(self.network.gas.transfer_gas_price);
 
-        let wallets = Arc::new(Mutex::new(HashMap::new()));
-        let keystore_dir = Arc::new(keystore_dir.to_path_buf());
+        let swept = try_join_all(chunkers.iter().zip(balances).map(|(chunker, balance)| {
+            let chunker = BundlerSigner::new(chunker.clone());
+            async move {
+                if balance.balance <= fee {
+                    return Ok::<_, Error>(None);
+                }
+                transfer_wvm(
+                    &chunker,
+                    to,
+                    balance.balance - fee,
+                    TX_BASE_GAS,
+                    &self.network,
+                )
+                .await
+                .map(Some)
+            }
+        }))
+        .await?;
 
-        let mut handles = Vec::with_capacity(amount as usize);
+        // dust under the transfer fee can't be recovered, those are drained too
+        if let (Some(status), Some(path)) = (retention.status(), self.keystore_path.as_ref()) {
+            let mut index = KeystoreIndex::load(path)?.unwrap_or_default();
+            for chunker in chunkers {
+                if self.retire_keystore(chunker.address(), retention)? {
+                    index.set_status(chunker.address(), status);
+                }
+            }
+            index.save(path)?;
+        }
+
+        Ok(swept.into_iter().flatten().collect())
+    }
 
-        for i in 0..amount {
-            let wallets = Arc::clone(&wallets);
-            let keystore_dir = Arc::clone(&keystore_dir);
-            let password = pwd.to_string();
+    /// Archives or deletes the keystore file of a loaded chunker, `false` if
+    /// it wasn't loaded from the keystore.
+    fn retire_keystore(
+        &self,
+        address: Address,
+        retention: KeystoreRetention,
+    ) -> Result<bool, Error> {
+        let Some(keystore) = self.keystores.get(&address) else {
+            return Ok(false);
+        };
+        let io_error =
+            |err: std::io::Error| Error::Other(format!("{}: {}", keystore.display(), err));
 
-            let handle = task::spawn(async move {
+        match retention {
+            KeystoreRetention::Keep => return Ok(false),
+            KeystoreRetention::Delete => fs::remove_file(keystore).map_err(io_error)?,
+            KeystoreRetention::Archive => {
+                let archive = keystore.with_file_name("archive");
+                fs::create_dir_all(&archive).map_err(io_error)?;
+                let file_name = keystore.file_name().unwrap_or_default();
+                fs::rename(keystore, archive.join(file_name)).map_err(io_error)?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub fn funder_signer(&self) -> Result<BundlerSigner, Error> {
+        let funder = self.funder.ok_or(Error::PrivateKeyNeeded)?;
+        let signer = LocalSigner::from_bytes(&funder).map_err(|_| Error::PrivateKeyParsingError)?;
+        Ok(BundlerSigner::new(signer))
+    }
+
+    async fn transfer(
+        &self,
+        funder: &BundlerSigner,
+        to: Address,
+        amount: U256,
+    ) -> Result<String, Error> {
+        transfer_wvm(
+            funder,
+            to,
+            amount,
+            self.network.gas.transfer_gas_limit,
+            &self.network,
+        )
+        .await
+    }
+
+    /// Creates `amount` chunkers after the existing ones, recording them in
+    /// the keystore index. Chunkers that could be encrypted are recorded even
+    /// when another one failed.
+    pub async fn create_chunkers(self, amount: u32) -> Result<Self, Error> {
+        let (keystore_dir, pwd) = self.keystore()?;
+        fs::create_dir_all(&keystore_dir).map_err(|err| Error::Other(err.to_string()))?;
+
+        let mut index = Self::keystore_index(&keystore_dir, &pwd).await?;
+        // keystores written before a crash kept them out of the index are skipped
+        let indexes: Vec<u32> = (index.next_index()..)
+            .filter(|i| !keystore_dir.join(KeystoreEntry::file_name(*i)).exists())
+            .take(amount as usize)
+            .collect();
+
+        let handles = indexes.into_iter().map(|i| {
+            let keystore_dir = keystore_dir.clone();
+            let password = pwd.clone();
+
+            task::spawn_blocking(move || {
                 let mut rng = thread_rng();
                 let mut bytes = [0u8; 32];
                 rng.fill_bytes(&mut bytes);
                 let private_key: alloy::primitives::FixedBytes<32> = B256::from(bytes);
 
                 // encrypt to keystore
-                if let Ok((signer, keystore_path)) = LocalSigner::encrypt_keystore(
-                    keystore_dir.as_ref(),
+                let (signer, _) = LocalSigner::encrypt_keystore(
+                    &keystore_dir,
                     &mut rng,
                     private_key,
                     &password,
-                    Some(format!("wallet_{}.json", i).as_str()),
-                ) {
-                    let address: Address = signer.address();
-                    let full_path = keystore_dir.join(&keystore_path);
-
-                    {
-                        let mut wallets_lock = wallets.lock().unwrap();
-                        wallets_lock.insert(address, full_path);
-                    }
-                }
-            });
+                    Some(KeystoreEntry::file_name(i).as_str()),
+                )?;
 
-            handles.push(handle);
+                Ok::<_, Error>(KeystoreEntry::new(i, signer.address()))
+            })
+        });
+
+        let mut failure = None;
+        for created in join_all(handles).await {
+            match created.map_err(|e| Error::Other(e.to_string())) {
+                Ok(Ok(entry)) => index.push(entry),
+                Ok(Err(e)) | Err(e) => failure = failure.or(Some(e)),
+            }
         }
-        join_all(handles).await;
+        index.save(&keystore_dir)?;
 
-        Ok(self)
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(self),
+        }
     }
 
+    /// Loads the first `input_count` active chunkers of the keystore index,
+    /// all of them if `None`, decrypting their keystores concurrently. With a
+    /// mnemonic, the first `input_count` chunkers of the derivation range are
+    /// derived instead.
     pub async fn load_chunkers(mut self, input_count: Option<u32>) -> Result<Self, Error> {
-        let mut chunkers: Vec<Chunker> = Vec::new();
+        if let Some(phrase) = self.mnemonic.clone() {
+            let range = self
+                .derivation_range
+                .clone()
+                .unwrap_or(0..HD_CHUNKERS_COUNT);
+            let indexes = range.take(input_count.map_or(usize::MAX, |count| count as usize));
+            self.chunkers = Some(self.derive_chunkers(&phrase, indexes).await?);
+            return Ok(self);
+        }
+
+        let (keystore_dir, pwd) = self.keystore()?;
+        let index = Self::keystore_index(&keystore_dir, &pwd).await?;
+
+        let entries = index
+            .active()
+            .take(input_count.map_or(usize::MAX, |count| count as usize))
+            .cloned()
+            .collect();
+        let loaded = Self::decrypt_keystores(&keystore_dir, &pwd, entries).await?;
+
+        let mut chunkers: Vec<Chunker> = Vec::with_capacity(loaded.len());
+        for (entry, chunker) in loaded {
+            if chunker.address() != entry.address {
+                return Err(Error::CorruptKeystore {
+                    path: keystore_dir.join(&entry.file).display().to_string(),
+                    reason: format!("holds {}, indexed as {}", chunker.address(), entry.address),
+                });
+            }
+            self.keystores
+                .insert(entry.address, keystore_dir.join(&entry.file));
+            chunkers.push(chunker);
+        }
+
+        self.chunkers = Some(chunkers);
+
+        Ok(self)
+    }
+
+    async fn derive_chunkers(
+        &self,
+        phrase: &str,
+        indexes: impl Iterator<Item = u32>,
+    ) -> Result<Vec<Chunker>, Error> {
         let path = self
+            .derivation_path
             .clone()
-            .keystore_path
-            .ok_or_else(|| Error::InvalidKeystore)?;
-        let pwd = self
-            .clone()
-            .keystore_pwd
-            .ok_or_else(|| Error::InvalidKeystore)?;
+            .unwrap_or(HD_DERIVATION_PATH.to_string());
+
+        let handles = indexes.map(|i| {
+            let phrase = phrase.to_string();
+            let path = format!("{}/{}", path, i);
+
+            task::spawn_blocking(move || {
+                MnemonicBuilder::<English>::default()
+                    .phrase(phrase)
+                    .derivation_path(&path)
+                    .and_then(|builder| builder.build())
+                    .map_err(|e| Error::InvalidMnemonic(format!("{}: {}", path, e)))
+            })
+        });
+
+        join_all(handles)
+            .await
+            .into_iter()
+            .map(|derived| derived.map_err(|e| Error::Other(e.to_string()))?)
+            .collect()
+    }
 
-        let keystore_dir = Path::new(&path);
-        let mut count = fs::read_dir(keystore_dir)
+    fn keystore(&self) -> Result<(PathBuf, String), Error> {
+        let path = self.keystore_path.clone().ok_or(Error::InvalidKeystore)?;
+        let pwd = self.keystore_pwd.clone().ok_or(Error::InvalidKeystore)?;
+        Ok((PathBuf::from(path), pwd))
+    }
+
+    /// The keystore index of `keystore_dir`. Keystores created before the
+    /// index existed are indexed from their `wallet_{i}.json` files.
+    async fn keystore_index(keystore_dir: &Path, pwd: &str) -> Result<KeystoreIndex, Error> {
+        if let Some(index) = KeystoreIndex::load(keystore_dir)? {
+            return Ok(index);
+        }
+
+        let entries = fs::read_dir(keystore_dir)
             .map_err(|err| Error::Other(err.to_string()))?
             .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                entry.path().is_file()
-                    && entry.path().extension().map_or(false, |ext| ext == "json")
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| {
+                let i: u32 = entry
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("wallet_")?
+                    .strip_suffix(".json")?
+                    .parse()
+                    .ok()?;
+                let created_at = entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()?
+                    .duration_since(UNIX_EPOCH)
+                    .ok()?
+                    .as_secs();
+                // the address is only known once decrypted
+                Some(KeystoreEntry {
+                    created_at,
+                    ..KeystoreEntry::new(i, Address::ZERO)
+                })
             })
-            .count();
+            .collect();
 
-        if input_count.is_some() {
-            count = input_count.unwrap() as usize;
+        let mut index = KeystoreIndex::default();
+        for (entry, chunker) in Self::decrypt_keystores(keystore_dir, pwd, entries).await? {
+            index.push(KeystoreEntry {
+                address: chunker.address(),
+                ..entry
+            });
         }
-
-        for i in 0..count {
-            let keystore_path = keystore_dir.join(format!("wallet_{}.json", i));
-
-            if keystore_path.exists() {
-                let recovered_signer = LocalSigner::decrypt_keystore(&keystore_path, &pwd)?;
-                println!(
-                    "Loaded chunker wallet_{}: {} from {:?}",
-                    i,
-                    recovered_signer.address(),
-                    keystore_path
-                );
-                chunkers.push(recovered_signer);
-            } else {
-                println!(
-                    "Keystore file for chunker wallet_{} not found at {:?}",
-                    i, keystore_path
-                );
-            }
+        if !index.chunkers.is_empty() {
+            index.save(keystore_dir)?;
         }
 
-        self.chunkers = Some(chunkers);
+        Ok(index)
+    }
 
-        Ok(self)
+    async fn decrypt_keystores(
+        keystore_dir: &Path,
+        pwd: &str,
+        entries: Vec<KeystoreEntry>,
+    ) -> Result<Vec<(KeystoreEntry, Chunker)>, Error> {
+        let handles = entries.into_iter().map(|entry| {
+            let path = keystore_dir.join(&entry.file);
+            let pwd = pwd.to_string();
+
+            task::spawn_blocking(move || {
+                if !path.is_file() {
+                    return Err(Error::KeystoreNotFound(path.display().to_string()));
+                }
+                let chunker = LocalSigner::decrypt_keystore(&path, &pwd).map_err(|e| {
+                    Error::CorruptKeystore {
+                        path: path.display().to_string(),
+                        reason: e.to_string(),
+                    }
+                })?;
+                Ok((entry, chunker))
+            })
+        });
+
+        join_all(handles)
+            .await
+            .into_iter()
+            .map(|decrypted| decrypted.map_err(|e| Error::Other(e.to_string()))?)
+            .collect()
     }
 }
+
+/// Funds the `chunkers` holding less than `min_balance` up to `target_balance`
+/// from `funder`, concurrently. Returns the top up txs hashes.
+pub async fn top_up_chunkers(
+    funder: &BundlerSigner,
+    chunkers: &[Address],
+    min_balance: U256,
+    target_balance: U256,
+    network: &NetworkConfig,
+) -> Result<Vec<String>, Error> {
+    let provider = create_evm_http_client(&network.rpc_url).await?;
+    let balances = try_join_all(
+        chunkers
+            .iter()
+            .map(|address| provider.get_balance(*address).into_future()),
+    )
+    .await?;
+
+    try_join_all(
+        chunkers
+            .iter()
+            .zip(balances)
+            .filter(|(_, balance)| *balance < min_balance && *balance < target_balance)
+            .map(|(address, balance)| {
+                transfer_wvm(
+                    funder,
+                    *address,
+                    target_balance - balance,
+                    network.gas.transfer_gas_limit,
+                    network,
+                )
+            }),
+    )
+    .await
+}
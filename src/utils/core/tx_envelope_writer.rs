@@ -3,7 +3,7 @@ use crate::utils::core::envelope::Envelope;
 use crate::utils::core::envelope_signature::EnvelopeSignature;
 use crate::utils::core::tags::Tag;
 use crate::utils::errors::Error;
-use alloy::consensus::{Signed, Transaction, TxEnvelope, TxLegacy};
+use alloy::consensus::{SignableTransaction, Signed, Transaction, TxEnvelope, TxLegacy};
 use alloy::primitives::{Address, Bytes, U256};
 use alloy::signers::Signature;
 use borsh::{from_slice, to_vec};
@@ -36,7 +36,7 @@ pub struct TxEnvelopeWrapper {
 
 impl TxEnvelopeWrapper {
     pub fn from_envelope(envelope: TxEnvelope, envelope_metadata: Envelope) -> Self {
-        let sig: alloy::signers::Signature = envelope.signature().clone();
+        let sig: alloy::signers::Signature = *envelope.signature();
 
         let env_sig = EnvelopeSignature {
             y_parity: sig.v(),
@@ -58,16 +58,97 @@ impl TxEnvelopeWrapper {
         }
     }
 
+    /// Wraps an already signed legacy `TxEnvelope`, e.g. one produced by the
+    /// envelope owner's own wallet. `tags` are not covered by the signature.
+    pub fn try_from_tx_envelope(
+        envelope: TxEnvelope,
+        tags: Option<Vec<Tag>>,
+    ) -> Result<Self, Error> {
+        if !envelope.is_legacy() {
+            return Err(Error::EnvelopeVerification(
+                "envelopes MUST be legacy transactions".to_string(),
+            ));
+        }
+        let chain_id = envelope.chain_id().ok_or_else(|| {
+            Error::EnvelopeVerification("envelopes MUST be EIP-155 signed".to_string())
+        })?;
+
+        let sig: Signature = *envelope.signature();
+
+        Ok(TxEnvelopeWrapper {
+            chain_id,
+            nonce: envelope.nonce(),
+            gas_limit: envelope.gas_limit(),
+            gas_price: envelope.gas_price().unwrap_or_default(),
+            to: envelope.to().map(|to| to.to_string()).unwrap_or_default(),
+            value: envelope.value().to_string(),
+            input: envelope.input().to_string(),
+            hash: envelope.tx_hash().to_string(),
+            signature: EnvelopeSignature {
+                y_parity: sig.v(),
+                r: sig.r().to_string(),
+                s: sig.s().to_string(),
+            },
+            tags,
+        })
+    }
+
     pub fn to_tx_envelope(&self) -> Result<TxEnvelope, Error> {
-        let to_address =
-            if self.to.is_empty() || self.to == "0x0000000000000000000000000000000000000000" {
-                None
-            } else {
-                Some(
-                    Address::from_str(&self.to)
-                        .map_err(|_| Error::Other("Failed to parse to address".to_string()))?,
-                )
-            };
+        let (tx, signature) = self.to_tx_legacy()?;
+
+        let hash_str = if self.hash.starts_with("0x") {
+            &self.hash
+        } else {
+            return Err(Error::Other("Hash should start with 0x".to_string()));
+        };
+
+        let hash = U256::from_str(hash_str)
+            .map_err(|_| Error::Other("Failed to parse transaction hash".to_string()))?;
+
+        let signed_tx = Signed::new_unchecked(tx, signature, hash.into());
+        let tx_envelope = TxEnvelope::Legacy(signed_tx);
+
+        Ok(tx_envelope)
+    }
+
+    /// Signs the envelope fields with the stored signature, recomputing the
+    /// tx hash instead of trusting the stored one.
+    pub fn to_signed_legacy(&self) -> Result<Signed<TxLegacy>, Error> {
+        let (tx, signature) = self.to_tx_legacy()?;
+        Ok(tx.into_signed(signature))
+    }
+
+    /// Recomputes the legacy tx hash from the envelope fields, checks it
+    /// against the stored hash and returns the owner recovered from the
+    /// signature.
+    pub fn verify(&self) -> Result<Address, Error> {
+        let signed = self
+            .to_signed_legacy()
+            .map_err(|e| Error::EnvelopeVerification(e.to_string()))?;
+
+        let computed = signed.hash().to_string();
+        if !computed.eq_ignore_ascii_case(&self.hash) {
+            return Err(Error::EnvelopeVerification(format!(
+                "stored hash {} does not match computed hash {}",
+                self.hash, computed
+            )));
+        }
+
+        signed
+            .recover_signer()
+            .map_err(|e| Error::EnvelopeVerification(e.to_string()))
+    }
+
+    pub fn to_tx_legacy(&self) -> Result<(TxLegacy, Signature), Error> {
+        // envelopes without a target are signed with the ZERO address as `to`
+        let to_address = if self.to.is_empty() {
+            None
+        } else {
+            Some(
+                Address::from_str(&self.to)
+                    .map_err(|_| Error::Other("Failed to parse to address".to_string()))?,
+            )
+        };
 
         let value = U256::from_str(&self.value)
             .map_err(|_| Error::Other("Failed to parse transaction value".to_string()))?;
@@ -89,29 +170,17 @@ impl TxEnvelopeWrapper {
 
         let signature = Signature::from_scalars_and_parity(r.into(), s.into(), recovery_id);
 
-        let hash_str = if self.hash.starts_with("0x") {
-            &self.hash
-        } else {
-            return Err(Error::Other("Hash should start with 0x".to_string()));
-        };
-
-        let hash = U256::from_str(hash_str)
-            .map_err(|_| Error::Other("Failed to parse transaction hash".to_string()))?;
-
         let tx = TxLegacy {
             chain_id: Some(self.chain_id),
             nonce: self.nonce,
-            gas_price: self.gas_price as u128,
+            gas_price: self.gas_price,
             gas_limit: self.gas_limit,
             to: to_address.into(),
             value,
             input,
         };
 
-        let signed_tx = Signed::new_unchecked(tx, signature, hash.into());
-        let tx_envelope = TxEnvelope::Legacy(signed_tx);
-
-        Ok(tx_envelope)
+        Ok((tx, signature))
     }
 
     pub fn brotli_compress_stream<R: Read>(reader: &mut R) -> Vec<u8> {
@@ -129,11 +198,12 @@ impl TxEnvelopeWrapper {
         writer.into_inner()
     }
 
-    pub fn brotli_decompress_stream<R: Read>(reader: &mut R) -> Vec<u8> {
+    pub fn brotli_decompress_stream<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
         let mut writer = Vec::new();
         let mut decoder = brotli::Decompressor::new(reader, 65_536);
-        std::io::copy(&mut decoder, &mut writer).unwrap();
-        writer
+        std::io::copy(&mut decoder, &mut writer)
+            .map_err(|e| Error::BrotliDecompression(e.to_string()))?;
+        Ok(writer)
     }
 
     pub fn brotli_compress(input: &[u8]) -> Vec<u8> {
@@ -148,21 +218,20 @@ impl TxEnvelopeWrapper {
         writer.into_inner()
     }
 
-    pub fn brotli_decompress(input: Vec<u8>) -> Vec<u8> {
+    pub fn brotli_decompress(input: Vec<u8>) -> Result<Vec<u8>, Error> {
         let mut decompressed_data = Vec::new();
         let mut decompressor = brotli::Decompressor::new(input.as_slice(), 32_768); // 32_768 -- 32 KiB
 
         decompressor
             .read_to_end(&mut decompressed_data)
-            .expect("Decompression failed");
-        decompressed_data
+            .map_err(|e| Error::BrotliDecompression(e.to_string()))?;
+        Ok(decompressed_data)
     }
     pub fn borsh_ser(input: &BundleData) -> Vec<u8> {
         to_vec(input).unwrap()
     }
-    pub fn borsh_der(input: Vec<u8>) -> BundleData {
-        let res: BundleData = from_slice(&input).expect("error deseriliazing the calldata");
-        res
+    pub fn borsh_der(input: Vec<u8>) -> Result<BundleData, Error> {
+        from_slice(&input).map_err(|e| Error::BorshDeserialization(e.to_string()))
     }
 }
 
@@ -0,0 +1,160 @@
+use crate::utils::core::large_bundle_manifest::ChunkEntry;
+use crate::utils::errors::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JournalRecord {
+    Chunk {
+        index: usize,
+        #[serde(flatten)]
+        chunk: ChunkEntry,
+    },
+    Finalized {
+        large_bundle_txid: String,
+    },
+}
+
+/// Append-only JSON lines file recording each propagated chunk as it lands,
+/// so an interrupted Large Bundle upload can resume without paying twice.
+#[derive(Debug)]
+pub struct UploadJournal {
+    path: PathBuf,
+    file: File,
+    chunks: HashMap<usize, ChunkEntry>,
+    finalized: Option<String>,
+}
+
+impl UploadJournal {
+    /// Opens the journal at `path`, creating it if needed and loading the
+    /// records of a previous run.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| Error::Journal(e.to_string()))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| Error::Journal(e.to_string()))?;
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)
+            .map_err(|e| Error::Journal(e.to_string()))?;
+        // a crash can leave a truncated last line, its chunk is simply re-sent
+        // and new records must start on their own line
+        if content.last().is_some_and(|byte| *byte != b'\n') {
+            file.write_all(b"\n")
+                .map_err(|e| Error::Journal(e.to_string()))?;
+        }
+
+        let mut chunks = HashMap::new();
+        let mut finalized = None;
+        for line in content.split(|byte| *byte == b'\n') {
+            match serde_json::from_slice::<JournalRecord>(line) {
+                Ok(JournalRecord::Chunk { index, chunk }) => {
+                    chunks.insert(index, chunk);
+                }
+                Ok(JournalRecord::Finalized { large_bundle_txid }) => {
+                    finalized = Some(large_bundle_txid)
+                }
+                Err(_) => continue,
+            }
+        }
+
+        Ok(Self {
+            path,
+            file,
+            chunks,
+            finalized,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn chunks_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn finalized(&self) -> Option<&String> {
+        self.finalized.as_ref()
+    }
+
+    /// The recorded chunk at `index`, if it was recorded for the same data.
+    pub fn confirmed(&self, index: usize, hash: &str) -> Option<&ChunkEntry> {
+        self.chunks
+            .get(&index)
+            .filter(|chunk| chunk.hash.eq_ignore_ascii_case(hash))
+    }
+
+    pub fn record_chunk(&mut self, index: usize, chunk: ChunkEntry) -> Result<(), Error> {
+        self.append(&JournalRecord::Chunk {
+            index,
+            chunk: chunk.clone(),
+        })?;
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    pub fn record_finalized(&mut self, large_bundle_txid: String) -> Result<(), Error> {
+        self.append(&JournalRecord::Finalized {
+            large_bundle_txid: large_bundle_txid.clone(),
+        })?;
+        self.finalized = Some(large_bundle_txid);
+        Ok(())
+    }
+
+    fn append(&mut self, record: &JournalRecord) -> Result<(), Error> {
+        let mut line = serde_json::to_vec(record).map_err(|e| Error::Journal(e.to_string()))?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data())
+            .map_err(|e| Error::Journal(e.to_string()))
+    }
+}
+
+/// An `UploadJournal` shared by the tasks of an upload. Records are written
+/// on the blocking pool, so their fsync never stalls a runtime worker.
+#[derive(Debug, Clone)]
+pub struct SharedJournal(Arc<Mutex<UploadJournal>>);
+
+impl SharedJournal {
+    pub fn new(journal: UploadJournal) -> Self {
+        Self(Arc::new(Mutex::new(journal)))
+    }
+
+    pub fn confirmed(&self, index: usize, hash: &str) -> Option<ChunkEntry> {
+        self.0.lock().unwrap().confirmed(index, hash).cloned()
+    }
+
+    pub async fn record_chunk(&self, index: usize, chunk: ChunkEntry) -> Result<(), Error> {
+        self.write(move |journal| journal.record_chunk(index, chunk))
+            .await
+    }
+
+    pub async fn record_finalized(&self, large_bundle_txid: String) -> Result<(), Error> {
+        self.write(move |journal| journal.record_finalized(large_bundle_txid))
+            .await
+    }
+
+    async fn write<F>(&self, record: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut UploadJournal) -> Result<(), Error> + Send + 'static,
+    {
+        let journal = Arc::clone(&self.0);
+        tokio::task::spawn_blocking(move || record(&mut journal.lock().unwrap()))
+            .await
+            .map_err(|e| Error::Journal(e.to_string()))?
+    }
+}
@@ -0,0 +1,67 @@
+use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::errors::Error;
+use serde::{Deserialize, Serialize};
+
+/// What to do with retrieved envelopes that fail `TxEnvelopeWrapper::verify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationMode {
+    // fail the whole retrieval on the first unverified envelope
+    Reject,
+    // keep every envelope, recording why verification failed
+    Flag,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedEnvelope {
+    #[serde(flatten)]
+    pub envelope: TxEnvelopeWrapper,
+    pub owner: Option<String>,
+    pub verification_error: Option<String>,
+}
+
+impl VerifiedEnvelope {
+    pub fn is_verified(&self) -> bool {
+        self.verification_error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedBundleData {
+    pub envelopes: Vec<VerifiedEnvelope>,
+}
+
+impl VerifiedBundleData {
+    pub fn verify(bundle: BundleData, mode: VerificationMode) -> Result<Self, Error> {
+        let mut envelopes = Vec::with_capacity(bundle.envelopes.len());
+
+        for (index, envelope) in bundle.envelopes.into_iter().enumerate() {
+            let verified = match envelope.verify() {
+                Ok(owner) => VerifiedEnvelope {
+                    owner: Some(owner.to_checksum(Some(envelope.chain_id))),
+                    verification_error: None,
+                    envelope,
+                },
+                Err(e) if mode == VerificationMode::Reject => {
+                    return Err(Error::EnvelopeRuleViolation {
+                        index,
+                        rule: e.to_string(),
+                    })
+                }
+                Err(e) => VerifiedEnvelope {
+                    envelope,
+                    owner: None,
+                    verification_error: Some(e.to_string()),
+                },
+            };
+            envelopes.push(verified);
+        }
+
+        Ok(Self { envelopes })
+    }
+
+    pub fn all_verified(&self) -> bool {
+        self.envelopes.iter().all(|e| e.is_verified())
+    }
+}
@@ -9,12 +9,50 @@ use thiserror::Error;
 pub enum Error {
     #[error("Invalid Keystore Path or Password")]
     InvalidKeystore,
+    #[error("Keystore file not found: {0}")]
+    KeystoreNotFound(String),
+    #[error("Keystore {path} could not be loaded: {reason}")]
+    CorruptKeystore { path: String, reason: String },
+    #[error("Invalid mnemonic or derivation path: {0}")]
+    InvalidMnemonic(String),
+    #[error("Keystore index error: {0}")]
+    KeystoreIndex(String),
     #[error("Bundle must have envelopes")]
     EnvelopesNeeded,
     #[error("Bundle or envelope must have a private key")]
     PrivateKeyNeeded,
     #[error("Bundle was not able to be retrieved")]
     BundleRetrievalProblem,
+    #[error("Bundle calldata is not valid hex: {0}")]
+    InvalidBundleHex(String),
+    #[error("Bundle calldata could not be brotli decompressed: {0}")]
+    BrotliDecompression(String),
+    #[error("Bundle data could not be borsh deserialized: {0}")]
+    BorshDeserialization(String),
+    #[error("Envelope {index} violates bundle rules: {rule}")]
+    EnvelopeRuleViolation { index: usize, rule: String },
+    #[error("Envelope failed verification: {0}")]
+    EnvelopeVerification(String),
+    #[error("Compressed bundle of {0} bytes exceeds the bundle size limit")]
+    BundleTooLarge(usize),
+    #[error("Large Bundle data exceeds the {0} bytes limit")]
+    LargeBundleTooLarge(u64),
+    #[error("Large Bundle chunk {index} failed verification: {reason}")]
+    ChunkIntegrity { index: usize, reason: String },
+    #[error("Large Bundle manifest failed verification: {0}")]
+    ManifestIntegrity(String),
+    #[error("Large Bundle hash {actual} does not match the manifest hash {expected}")]
+    LargeBundleHashMismatch { expected: String, actual: String },
+    #[error("Large Bundle chunk {index} was not included: {reason}")]
+    ChunkNotIncluded { index: usize, reason: String },
+    #[error("Large Bundle manifest part {index} was not included: {reason}")]
+    ManifestPartNotIncluded { index: usize, reason: String },
+    #[error("Nonce of {address} still too low after {resyncs} resyncs")]
+    NonceResyncsExhausted { address: String, resyncs: u32 },
+    #[error("Bundle tx may cost {cost} wei, over the {max} wei maximum spend")]
+    FeeCapExceeded { cost: u128, max: u128 },
+    #[error("Upload journal error: {0}")]
+    Journal(String),
     #[error("Address is not verified")]
     UnverifiedAddress,
     #[error("Bundle could not be created")]
@@ -27,12 +65,18 @@ pub enum Error {
     SuperAccountNeeded,
     #[error("SuperAccount instance missing chunkers")]
     ChunkersNeeded,
+    #[error("No funded chunker left to propagate chunks")]
+    NoFundedChunkers,
+    #[error("Bundle submission is disabled, no payer is configured")]
+    SubmissionDisabled,
     #[error("Other")]
     Other(String),
     #[error("Error parsing private key")]
     PrivateKeyParsingError,
     #[error("Invalid RPC Url")]
     InvalidRpcUrl,
+    #[error("Invalid network config: {0}")]
+    InvalidNetworkConfig(String),
     #[error("There's been an issue with the current RPC call")]
     RpcTransportError(#[from] RpcError<TransportErrorKind>),
     #[error("Hex could not be parsed")]
@@ -1,21 +1,25 @@
+use crate::utils::constants::MAX_NONCE_RESYNCS;
 use crate::utils::core::bundle_data::BundleData;
 use crate::utils::core::bundle_tx_metadata::BundleTxMetadata;
+use crate::utils::core::bundle_validator::BundleValidator;
 use crate::utils::core::envelope::Envelope;
+use crate::utils::core::signed_bundle::{BundleFees, EncodedBundle};
 use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
 use crate::utils::errors::Error;
-use alloy::signers::Signer;
+use crate::utils::network::NetworkConfig;
+use crate::utils::nonce_manager::NonceManager;
+use crate::utils::signer::BundlerSigner;
 use {
-    crate::utils::constants::{ADDRESS_BABE1, BLOCK_GAS_LIMIT, CHAIN_ID, WVM_RPC_URL},
     alloy::{
         consensus::TxEnvelope,
-        network::{EthereumWallet, TransactionBuilder},
+        eips::eip2718::Encodable2718,
+        network::TransactionBuilder,
         primitives::{Address, B256, U256},
         providers::{Provider, ProviderBuilder, RootProvider},
-        rpc::types::{Transaction, TransactionRequest},
-        signers::local::{LocalSigner, PrivateKeySigner},
+        rpc::types::TransactionRequest,
+        signers::local::LocalSigner,
         transports::http::{Client, Http},
     },
-    eyre::OptionExt,
     futures::future::join_all,
     hex,
     rand::{thread_rng, Rng, RngCore},
@@ -34,154 +38,237 @@ pub async fn create_evm_http_client(rpc_url: &str) -> Result<HttpClient, Error>
 }
 
 pub async fn create_envelope(
-    private_key: Option<&str>,
+    signer: &BundlerSigner,
     envelope: Envelope,
+    network: &NetworkConfig,
 ) -> Result<TxEnvelope, Error> {
-    if let Some(priv_key) = private_key {
-        let signer: PrivateKeySigner = priv_key
-            .parse()
-            .map_err(|_| Error::PrivateKeyParsingError)?;
-        let wallet = EthereumWallet::from(signer.clone());
-        let envelope_target_address = envelope
-            .target
-            .map(|t| t.parse::<Address>().unwrap_or(Address::ZERO))
-            .unwrap_or(Address::ZERO);
-
-        let envelope_data = envelope
-            .data
-            .ok_or_else(|| Error::Other("Data Required".to_string()))?;
-
-        let tx = TransactionRequest::default()
-            .with_to(envelope_target_address)
-            .with_nonce(0)
-            .with_chain_id(CHAIN_ID)
-            .with_input(envelope_data)
-            .with_value(U256::from(0))
-            .with_gas_limit(0)
-            .with_gas_price(0);
-
-        let tx_envelope: alloy::consensus::TxEnvelope = tx.build(&wallet).await?;
-        Ok(tx_envelope)
+    let wallet = signer.wallet();
+    let envelope_target_address = envelope
+        .target
+        .map(|t| t.parse::<Address>().unwrap_or(Address::ZERO))
+        .unwrap_or(Address::ZERO);
+
+    let envelope_data = envelope
+        .data
+        .ok_or_else(|| Error::Other("Data Required".to_string()))?;
+
+    let tx = TransactionRequest::default()
+        .with_to(envelope_target_address)
+        .with_nonce(0)
+        .with_chain_id(network.chain_id)
+        .with_input(envelope_data)
+        .with_value(U256::from(0))
+        .with_gas_limit(0)
+        .with_gas_price(0);
+
+    let tx_envelope: alloy::consensus::TxEnvelope = tx.build(&wallet).await?;
+    Ok(tx_envelope)
+}
+
+/// Signs the parallel-created envelopes of a bundle.
+pub async fn sign_envelopes(
+    envelope_inputs: Vec<Envelope>,
+    signer: &BundlerSigner,
+    network: &NetworkConfig,
+) -> Result<Vec<TxEnvelopeWrapper>, Error> {
+    let futures: Vec<_> = envelope_inputs
+        .into_iter()
+        .enumerate()
+        .map(|(i, input)| {
+            let signer = signer.clone();
+            let network = network.clone();
+            task::spawn(async move {
+                match create_envelope(&signer, input.clone(), &network).await {
+                    Ok(tx) => {
+                        println!("created tx count {}", i);
+                        Ok(TxEnvelopeWrapper::from_envelope(tx, input))
+                    }
+                    Err(e) => Err(e),
+                }
+            })
+        })
+        .collect();
+
+    join_all(futures)
+        .await
+        .into_iter()
+        .map(|r| r.map_err(|e| Error::Other(e.to_string()))?)
+        .collect()
+}
+
+/// Borsh-Brotli encodes signed envelopes and validates the resulting bundle.
+pub fn encode_bundle(
+    envelopes: Vec<TxEnvelopeWrapper>,
+    network: &NetworkConfig,
+    fast: bool,
+) -> Result<EncodedBundle, Error> {
+    let bundle = BundleData::from(envelopes);
+    let serialized = TxEnvelopeWrapper::borsh_ser(&bundle);
+    let compressed = if fast {
+        TxEnvelopeWrapper::brotli_compress_fast(&serialized)
     } else {
-        Err(Error::PrivateKeyNeeded)
-    }
+        TxEnvelopeWrapper::brotli_compress(&serialized)
+    };
+    BundleValidator::new(network)
+        .validate(&bundle, Some(compressed.len()))
+        .into_result()?;
+
+    Ok(EncodedBundle {
+        bundle,
+        serialized,
+        compressed,
+    })
+}
+
+/// Builds and signs the EIP-1559 bundle transaction without broadcasting it.
+pub async fn sign_bundle_tx(
+    calldata: Vec<u8>,
+    payer: &BundlerSigner,
+    version: &str,
+    nonce: u64,
+    fees: &BundleFees,
+    network: &NetworkConfig,
+) -> Result<TxEnvelope, Error> {
+    let wallet = payer.wallet();
+    let version_address = version.parse::<Address>()?;
+
+    let tx = TransactionRequest::default()
+        .with_to(version_address)
+        .with_nonce(nonce)
+        .with_chain_id(network.chain_id)
+        .with_input(calldata)
+        .with_value(U256::from(0))
+        .with_gas_limit(fees.gas_limit)
+        .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+        .with_max_fee_per_gas(fees.max_fee_per_gas);
+
+    Ok(tx.build(&wallet).await?)
+}
+
+pub fn encode_raw_tx(tx: &TxEnvelope) -> Vec<u8> {
+    tx.encoded_2718()
+}
+
+/// Submits an EIP-2718 encoded signed transaction as-is.
+pub async fn submit_raw_tx(
+    provider: &HttpClient,
+    raw_tx: &[u8],
+) -> Result<
+    alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum>,
+    Error,
+> {
+    Ok(provider.send_raw_transaction(raw_tx).await?)
 }
 
-async fn broadcast_bundle(
+pub async fn broadcast_bundle(
     envelopes: Vec<u8>,
     provider: &RootProvider<Http<Client>>,
-    private_key: Option<String>,
+    payer: &BundlerSigner,
     version: &str,
+    network: &NetworkConfig,
 ) -> Result<
     alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum>,
     Error,
 > {
-    if let Some(priv_key) = private_key {
-        let signer: PrivateKeySigner = priv_key.parse()?;
-        let wallet = EthereumWallet::from(signer.clone());
-        let address = signer.address();
-
-        let mut nonce = provider.get_transaction_count(address).await?;
-        let version_address = version.parse::<Address>()?;
-        let mut max_priority_fee_per_gas: u128 = 1_000_000_000;
-        let mut max_fee_per_gas: u128 = 2_000_000_000;
-
-        let mut attempt = 0;
-
-        let envelopes_ref = &envelopes;
-
-        loop {
-            attempt += 1;
-            // println!("Broadcast attempt: {}", attempt);
-
-            let tx = TransactionRequest::default()
-                .with_to(version_address)
-                .with_nonce(nonce)
-                .with_chain_id(CHAIN_ID)
-                .with_input(envelopes_ref.clone())
-                .with_value(U256::from(0))
-                .with_gas_limit(490_000_000)
-                .with_max_priority_fee_per_gas(max_priority_fee_per_gas)
-                .with_max_fee_per_gas(max_fee_per_gas);
-
-            let tx_envelope: alloy::consensus::TxEnvelope = tx.build(&wallet).await?;
-
-            match provider.send_tx_envelope(tx_envelope).await {
-                Ok(tx) => {
-                    println!("Transaction successfully broadcasted with nonce: {}", nonce);
-                    return Ok(tx);
-                }
-                Err(e)
-                    if e.to_string()
-                        .contains("replacement transaction underpriced") =>
-                {
-                    println!("Transaction underpriced, trying next nonce...");
-                    nonce += 1; // increment nonce if underpriced
-
-                    if max_fee_per_gas < BLOCK_GAS_LIMIT
-                        && max_priority_fee_per_gas < BLOCK_GAS_LIMIT
-                    {
-                        max_priority_fee_per_gas *= 11 / 10;
-                        max_fee_per_gas *= 11 / 10;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Unexpected error: {:?}", e);
-                    return Err(e.into());
-                }
+    let policy = &network.fees;
+    let mut fees = policy.bundle_fees(provider, &envelopes, network).await?;
+    let mut bumps = 0;
+    let mut resyncs = 0;
+    // nonces shared with the concurrent broadcasts paid by the same key
+    let nonces = NonceManager::global();
+    let address = payer.address();
+    // released on any early return, or if this future is dropped mid-flight
+    let mut nonce = nonces.next(provider, network.chain_id, address).await?;
+
+    loop {
+        let tx_envelope = sign_bundle_tx(
+            envelopes.clone(),
+            payer,
+            version,
+            nonce.nonce(),
+            &fees,
+            network,
+        )
+        .await?;
+
+        let error = match provider.send_tx_envelope(tx_envelope).await {
+            Ok(tx) => {
+                println!(
+                    "Transaction successfully broadcasted with nonce: {}",
+                    nonce.nonce()
+                );
+                nonce.confirm();
+                return Ok(tx);
+            }
+            Err(e) => e,
+        };
+        let message = error.to_string();
+
+        if message.contains("nonce too low") {
+            // another sender keeps taking the nonces, or the node lags behind
+            if resyncs == MAX_NONCE_RESYNCS {
+                return Err(Error::NonceResyncsExhausted {
+                    address: address.to_string(),
+                    resyncs,
+                });
             }
+            resyncs += 1;
+            println!("Nonce {} already used, resyncing...", nonce.nonce());
+            nonce.resync(provider).await?;
+            nonce = nonces.next(provider, network.chain_id, address).await?;
+            continue;
         }
-    } else {
-        Err(Error::PrivateKeyNeeded)
+
+        if !message.contains("underpriced") && !message.contains("less than block base fee") {
+            eprintln!("Unexpected error: {:?}", error);
+            return Err(error.into());
+        }
+        if bumps == policy.max_bumps {
+            println!("Transaction still underpriced after {} fee bumps", bumps);
+            return Err(error.into());
+        }
+
+        if message.contains("replacement transaction underpriced") {
+            println!(
+                "Nonce {} taken by a pending transaction, trying next nonce...",
+                nonce.nonce()
+            );
+            // the nonce is taken by a pending tx sent from elsewhere
+            nonce.confirm();
+            nonce = nonces.next(provider, network.chain_id, address).await?;
+        }
+        fees = policy.bump(&fees);
+        bumps += 1;
+        println!(
+            "Transaction underpriced, bumping fees by {}% ({}/{})",
+            policy.bump_percent, bumps, policy.max_bumps
+        );
+        policy.check_spend(&fees)?;
     }
 }
 
 pub async fn create_bundle(
     mut provider: Option<HttpClient>,
     envelope_inputs: Vec<Envelope>,
-    private_key: String,
+    signer: &BundlerSigner,
+    payer: &BundlerSigner,
     version: &str,
+    network: &NetworkConfig,
 ) -> Result<
     alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum>,
     Error,
 > {
     if provider.is_none() {
         println!("PROVIDER NOT PROVIDED");
-        provider = Some(create_evm_http_client(WVM_RPC_URL).await?);
+        provider = Some(create_evm_http_client(&network.rpc_url).await?);
     }
     let provider = std::sync::Arc::new(provider.unwrap());
-    let private_key = private_key.clone();
-
-    let futures: Vec<_> = envelope_inputs
-        .into_iter()
-        .enumerate()
-        .map(|(i, input)| {
-            let pk = private_key.clone();
-            task::spawn(async move {
-                match create_envelope(Some(&pk), input.clone()).await {
-                    Ok(tx) => {
-                        println!("created tx count {}", i);
-                        Ok(TxEnvelopeWrapper::from_envelope(tx, input))
-                    }
-                    Err(e) => Err(e),
-                }
-            })
-        })
-        .collect();
 
-    let results = join_all(futures).await;
-    let envelopes: Vec<TxEnvelopeWrapper> = results
-        .into_iter()
-        .filter_map(|r| r.ok())
-        .filter_map(|r| r.ok())
-        .collect();
-
-    let bundle = BundleData::from(envelopes.clone());
-    let serialized = TxEnvelopeWrapper::borsh_ser(&bundle);
-    let compressed = TxEnvelopeWrapper::brotli_compress(&serialized);
+    let envelopes = sign_envelopes(envelope_inputs, signer, network).await?;
+    let encoded = encode_bundle(envelopes, network, false)?;
 
     let tx: alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum> =
-        broadcast_bundle(compressed, &provider, Some(private_key), version).await?;
+        broadcast_bundle(encoded.compressed, &provider, payer, version, network).await?;
 
     Ok(tx)
 }
@@ -212,14 +299,17 @@ pub fn generate_random_bytes(length: usize) -> Vec<u8> {
     data
 }
 
-pub async fn retrieve_bundle_tx(txid: String) -> Result<BundleTxMetadata, Error> {
-    let provider = create_evm_http_client(WVM_RPC_URL).await?;
+pub async fn retrieve_bundle_tx(
+    txid: String,
+    network: &NetworkConfig,
+) -> Result<BundleTxMetadata, Error> {
+    let provider = create_evm_http_client(&network.rpc_url).await?;
     let txid = B256::from_str(&txid)?;
     let tx = provider
         .get_transaction_by_hash(txid)
         .await?
-        .ok_or_eyre("error retrieving tx");
-    let tx_json = serde_json::json!(&tx?);
+        .ok_or(Error::BundleRetrievalProblem)?;
+    let tx_json = serde_json::json!(&tx);
 
     let block_hash: &str = tx_json["blockHash"].as_str().unwrap_or("0x");
     let block_number_hex: &str = tx_json["blockNumber"].as_str().unwrap_or("0x");
@@ -238,60 +328,89 @@ pub async fn retrieve_bundle_tx(txid: String) -> Result<BundleTxMetadata, Error>
     Ok(res)
 }
 
-pub async fn retrieve_bundle_data(calldata: String) -> BundleData {
-    let byte_array = hex::decode(calldata.trim_start_matches("0x")).expect("decoding failed");
-    let unbrotli = TxEnvelopeWrapper::brotli_decompress(byte_array);
-    let unborsh: BundleData = TxEnvelopeWrapper::borsh_der(unbrotli);
+pub fn decode_bundle_data(calldata: &str) -> Result<BundleData, Error> {
+    let byte_array = hex::decode(calldata.trim_start_matches("0x"))
+        .map_err(|e| Error::InvalidBundleHex(e.to_string()))?;
+    let unbrotli = TxEnvelopeWrapper::brotli_decompress(byte_array)?;
+    TxEnvelopeWrapper::borsh_der(unbrotli)
+}
+
+pub async fn retrieve_bundle_data(calldata: String) -> Result<BundleData, Error> {
+    let unborsh: BundleData = decode_bundle_data(&calldata)?;
     // validate envelopes MUSTs
-    for envelope in &unborsh.envelopes {
-        assert_eq!(envelope.nonce, 0);
-        assert_eq!(envelope.gas_limit, 0);
-        assert_eq!(envelope.gas_price, 0);
+    for (index, envelope) in unborsh.envelopes.iter().enumerate() {
+        let rule = if envelope.nonce != 0 {
+            "nonce MUST be 0"
+        } else if envelope.gas_limit != 0 {
+            "gas_limit MUST be 0"
+        } else if envelope.gas_price != 0 {
+            "gas_price MUST be 0"
+        } else {
+            continue;
+        };
+
+        return Err(Error::EnvelopeRuleViolation {
+            index,
+            rule: rule.to_string(),
+        });
     }
 
-    unborsh
+    Ok(unborsh)
 }
 
-pub async fn sign_data(private_key: Option<&str>, data: Vec<u8>) -> Result<Vec<u8>, Error> {
-    if let Some(priv_key) = private_key {
-        let signer: PrivateKeySigner = priv_key
-            .parse()
-            .map_err(|_| Error::PrivateKeyParsingError)?;
-
-        let signed_msg = signer
-            .sign_message(&data)
-            .await
-            .map_err(|e| Error::Other(e.to_string()))?;
-        // [u8; 65] to Vec<u8>
-        Ok(signed_msg.as_bytes().to_vec())
-    } else {
-        Err(Error::PrivateKeyNeeded)
-    }
+pub async fn sign_data(signer: &BundlerSigner, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let signed_msg = signer.sign_message(&data).await?;
+    // [u8; 65] to Vec<u8>
+    Ok(signed_msg.as_bytes().to_vec())
 }
 
 pub async fn send_wvm(
     sender_pk: AlloyPk,
     address_to: Address,
     amount: u64,
+    network: &NetworkConfig,
 ) -> Result<String, Error> {
-    let gas_price = U256::from(1_200_000_000);
-    let amount_wei = U256::from(amount);
     let signer =
         LocalSigner::from_bytes(&sender_pk).map_err(|err| Error::Other(err.to_string()))?;
-    let wallet = EthereumWallet::from(signer.clone());
-    let rpc_url = WVM_RPC_URL.parse().map_err(|_| Error::InvalidRpcUrl)?;
-    let provider = ProviderBuilder::new().wallet(wallet).on_http(rpc_url);
-    let nonce = provider.get_transaction_count(signer.address()).await?;
+    transfer_wvm(
+        &BundlerSigner::new(signer),
+        address_to,
+        U256::from(amount),
+        network.gas.transfer_gas_limit,
+        network,
+    )
+    .await
+}
+
+/// Sends `amount` wei at the network's transfer gas price and waits for the
+/// receipt. Nonces come from the nonce manager, so transfers from the same
+/// sender can run concurrently.
+pub async fn transfer_wvm(
+    sender: &BundlerSigner,
+    address_to: Address,
+    amount: U256,
+    gas_limit: u64,
+    network: &NetworkConfig,
+) -> Result<String, Error> {
+    let rpc_url = network.rpc_url.parse().map_err(|_| Error::InvalidRpcUrl)?;
+    let provider = ProviderBuilder::new()
+        .wallet(sender.wallet())
+        .on_http(rpc_url);
+    let nonce = NonceManager::global()
+        .next(provider.root(), network.chain_id, sender.address())
+        .await?;
 
     let tx = TransactionRequest::default()
         .with_to(address_to)
-        .with_value(amount_wei)
-        .with_gas_price(gas_price.try_into().unwrap())
-        .with_gas_limit(500_000)
-        .with_nonce(nonce)
-        .gas_limit(BLOCK_GAS_LIMIT.try_into().unwrap());
+        .with_value(amount)
+        .with_gas_price(network.gas.transfer_gas_price)
+        .with_nonce(nonce.nonce())
+        .with_chain_id(network.chain_id)
+        .with_gas_limit(gas_limit);
 
+    // a rejected transfer releases its nonce as the guard drops
     let pending_tx = provider.send_transaction(tx).await?;
+    nonce.confirm();
     let receipt = pending_tx
         .get_receipt()
         .await
@@ -301,50 +420,28 @@ pub async fn send_wvm(
 }
 
 pub async fn create_bundle_sync(
-    mut provider: Option<HttpClient>,
+    provider: Option<HttpClient>,
     envelope_inputs: Vec<Envelope>,
-    private_key: String,
+    signer: &BundlerSigner,
+    payer: &BundlerSigner,
     version: &str,
+    network: &NetworkConfig,
 ) -> Result<
     alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum>,
     Error,
 > {
-    if provider.is_none() {
-        provider = Some(create_evm_http_client(WVM_RPC_URL).await?);
-    }
-    let provider = std::sync::Arc::new(provider.unwrap());
-    let mut envelopes = Vec::with_capacity(envelope_inputs.len());
-
-    for (i, input) in envelope_inputs.into_iter().enumerate() {
-        match create_envelope(Some(&private_key), input.clone()).await {
-            Ok(tx) => {
-                // println!("Created envelope {} in {:?}", i, elapsed);
-                envelopes.push(TxEnvelopeWrapper::from_envelope(tx, input));
-            }
-            Err(e) => {
-                println!("Failed to create envelope {}: {}", i, e);
-                return Err(e);
-            }
-        }
-    }
-
-    let bundle = BundleData::from(envelopes);
-    let serialized = TxEnvelopeWrapper::borsh_ser(&bundle);
-    let compressed = TxEnvelopeWrapper::brotli_compress_fast(&serialized);
+    let provider = match provider {
+        Some(provider) => provider,
+        None => create_evm_http_client(&network.rpc_url).await?,
+    };
+    let compressed = encode_envelopes_sync(envelope_inputs, signer, network).await?;
 
     const MAX_BROADCAST_RETRIES: usize = 3;
     let mut last_error = None;
 
     for attempt in 1..=MAX_BROADCAST_RETRIES {
         println!("Broadcast attempt: {}", attempt);
-        match broadcast_bundle(
-            compressed.clone(),
-            &provider,
-            Some(private_key.clone()),
-            version,
-        )
-        .await
-        {
+        match broadcast_bundle(compressed.clone(), &provider, payer, version, network).await {
             Ok(tx) => {
                 return Ok(tx);
             }
@@ -362,3 +459,48 @@ pub async fn create_bundle_sync(
 
     Err(last_error.unwrap_or(Error::Other("All broadcast attempts failed".to_string())))
 }
+
+/// `create_bundle_sync` with a single broadcast attempt, for callers that
+/// own the retries, such as the Large Bundle chunks scheduler.
+pub async fn create_bundle_sync_once(
+    provider: Option<HttpClient>,
+    envelope_inputs: Vec<Envelope>,
+    signer: &BundlerSigner,
+    payer: &BundlerSigner,
+    version: &str,
+    network: &NetworkConfig,
+) -> Result<
+    alloy::providers::PendingTransactionBuilder<Http<Client>, alloy::network::Ethereum>,
+    Error,
+> {
+    let provider = match provider {
+        Some(provider) => provider,
+        None => create_evm_http_client(&network.rpc_url).await?,
+    };
+    let compressed = encode_envelopes_sync(envelope_inputs, signer, network).await?;
+
+    broadcast_bundle(compressed, &provider, payer, version, network).await
+}
+
+async fn encode_envelopes_sync(
+    envelope_inputs: Vec<Envelope>,
+    signer: &BundlerSigner,
+    network: &NetworkConfig,
+) -> Result<Vec<u8>, Error> {
+    let mut envelopes = Vec::with_capacity(envelope_inputs.len());
+
+    for (i, input) in envelope_inputs.into_iter().enumerate() {
+        match create_envelope(signer, input.clone(), network).await {
+            Ok(tx) => {
+                // println!("Created envelope {} in {:?}", i, elapsed);
+                envelopes.push(TxEnvelopeWrapper::from_envelope(tx, input));
+            }
+            Err(e) => {
+                println!("Failed to create envelope {}: {}", i, e);
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(encode_bundle(envelopes, network, true)?.compressed)
+}
@@ -0,0 +1,163 @@
+use crate::utils::constants::{
+    CALLDATA_NONZERO_BYTE_GAS, CALLDATA_ZERO_BYTE_GAS, GAS_PRIORITY_MULTIPLIER, TX_BASE_GAS,
+};
+use crate::utils::core::signed_bundle::BundleFees;
+use crate::utils::errors::Error;
+use crate::utils::evm::HttpClient;
+use crate::utils::network::{GasPolicy, NetworkConfig};
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
+use serde::{Deserialize, Serialize};
+
+/// Intrinsic gas of a transaction carrying `calldata` (EIP-2028 pricing).
+pub fn calldata_gas(calldata: &[u8]) -> u64 {
+    calldata.iter().fold(TX_BASE_GAS, |gas, byte| {
+        gas + if *byte == 0 {
+            CALLDATA_ZERO_BYTE_GAS
+        } else {
+            CALLDATA_NONZERO_BYTE_GAS
+        }
+    })
+}
+
+/// How bundle transactions are priced: live EIP-1559 estimation, a gas limit
+/// derived from the calldata, fee bumps on underpriced errors and a cap on
+/// what a single bundle may cost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeePolicy {
+    // eth_feeHistory based fees, otherwise the static GasPolicy fees
+    pub estimate: bool,
+    // added on top of the calldata intrinsic gas
+    pub gas_limit_margin_percent: u64,
+    pub bump_percent: u64,
+    pub max_bumps: u32,
+    // in wei, gas_limit * max_fee_per_gas of a single bundle tx (u64 for TOML)
+    pub max_spend_per_bundle: Option<u64>,
+}
+
+impl Default for FeePolicy {
+    fn default() -> Self {
+        Self {
+            estimate: true,
+            gas_limit_margin_percent: 10,
+            bump_percent: ((GAS_PRIORITY_MULTIPLIER - 1.0) * 100.0).round() as u64,
+            max_bumps: 5,
+            max_spend_per_bundle: None,
+        }
+    }
+}
+
+impl FeePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn estimate(mut self, estimate: bool) -> Self {
+        self.estimate = estimate;
+        self
+    }
+
+    pub fn gas_limit_margin_percent(mut self, percent: u64) -> Self {
+        self.gas_limit_margin_percent = percent;
+        self
+    }
+
+    pub fn bump_percent(mut self, percent: u64) -> Self {
+        self.bump_percent = percent;
+        self
+    }
+
+    pub fn max_bumps(mut self, max_bumps: u32) -> Self {
+        self.max_bumps = max_bumps;
+        self
+    }
+
+    pub fn max_spend_per_bundle(mut self, wei: u64) -> Self {
+        self.max_spend_per_bundle = Some(wei);
+        self
+    }
+
+    /// Calldata intrinsic gas plus the margin, capped by the network's
+    /// `bundle_gas_limit`.
+    pub fn gas_limit(&self, calldata: &[u8], gas: &GasPolicy) -> u64 {
+        self.gas_limit_for(calldata_gas(calldata), gas)
+    }
+
+    /// `gas_limit` of a calldata whose intrinsic gas is `gas_used`.
+    pub fn gas_limit_for(&self, gas_used: u64, gas: &GasPolicy) -> u64 {
+        let gas_limit = gas_used * (100 + self.gas_limit_margin_percent) / 100;
+        gas_limit.min(gas.bundle_gas_limit)
+    }
+
+    /// Fees of a bundle transaction carrying `calldata`, rejected when over
+    /// `max_spend_per_bundle`.
+    pub async fn bundle_fees(
+        &self,
+        provider: &HttpClient,
+        calldata: &[u8],
+        network: &NetworkConfig,
+    ) -> Result<BundleFees, Error> {
+        let fees = self.estimate_fees(provider, calldata, network).await;
+        self.check_spend(&fees)?;
+        Ok(fees)
+    }
+
+    /// Fees of a bundle transaction carrying `calldata`. The static fees are
+    /// used when estimation is disabled or fails.
+    pub async fn estimate_fees(
+        &self,
+        provider: &HttpClient,
+        calldata: &[u8],
+        network: &NetworkConfig,
+    ) -> BundleFees {
+        let mut fees = BundleFees::from(&network.gas);
+        fees.gas_limit = self.gas_limit(calldata, &network.gas);
+
+        if self.estimate {
+            match provider.estimate_eip1559_fees(None).await {
+                Ok(estimation) => {
+                    fees.max_fee_per_gas = estimation.max_fee_per_gas;
+                    fees.max_priority_fee_per_gas = estimation.max_priority_fee_per_gas;
+                }
+                Err(e) => println!("Fee estimation failed ({}), using the static fees", e),
+            }
+        }
+
+        fees
+    }
+
+    /// Fees raised by `bump_percent`, by at least 1 wei.
+    pub fn bump(&self, fees: &BundleFees) -> BundleFees {
+        let bump = |fee: u128| {
+            let bumped = fee.saturating_mul(100 + self.bump_percent as u128) / 100;
+            bumped.max(fee.saturating_add(1))
+        };
+
+        BundleFees {
+            gas_limit: fees.gas_limit,
+            max_fee_per_gas: bump(fees.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(fees.max_priority_fee_per_gas),
+        }
+    }
+
+    /// Rejects fees whose worst case cost exceeds `max_spend_per_bundle`.
+    pub fn check_spend(&self, fees: &BundleFees) -> Result<(), Error> {
+        let cost = (fees.gas_limit as u128).saturating_mul(fees.max_fee_per_gas);
+        match self.max_spend_per_bundle {
+            Some(max) if cost > max as u128 => Err(Error::FeeCapExceeded {
+                cost,
+                max: max as u128,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Base fee per gas of the next block.
+pub async fn next_base_fee(provider: &HttpClient) -> Result<u128, Error> {
+    let fee_history = provider
+        .get_fee_history(1, BlockNumberOrTag::Latest, &[])
+        .await?;
+    Ok(fee_history.next_block_base_fee().unwrap_or_default())
+}
@@ -3,5 +3,10 @@ pub mod core;
 pub mod env_var;
 pub mod errors;
 pub mod evm;
-pub mod server;
+pub mod fees;
 pub mod load0;
+pub mod network;
+pub mod nonce_manager;
+pub mod remote_signer;
+pub mod server;
+pub mod signer;
@@ -0,0 +1,160 @@
+use crate::utils::constants::{
+    ADDRESS_BABE1, ADDRESS_BABE2, BLOCK_GAS_LIMIT, CHAIN_ID, WVM_RPC_URL,
+};
+use crate::utils::env_var::get_env_key;
+use crate::utils::errors::Error;
+use crate::utils::fees::FeePolicy;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Gas settings used when broadcasting bundles and tWVM transfers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GasPolicy {
+    pub bundle_gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    pub transfer_gas_limit: u64,
+    pub transfer_gas_price: u128,
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        Self {
+            bundle_gas_limit: 490_000_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            transfer_gas_limit: BLOCK_GAS_LIMIT as u64,
+            transfer_gas_price: 1_200_000_000,
+        }
+    }
+}
+
+/// Network profile the bundler talks to: RPC endpoint, chain id,
+/// bundle target addresses per version and gas settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    pub name: String,
+    pub rpc_url: String,
+    pub chain_id: u64,
+    // 0xbabe1 -- bundles v0.1.0
+    pub babe1: String,
+    // 0xbabe2 -- bundles v0.2.0 and Large Bundles
+    pub babe2: String,
+    #[serde(default)]
+    pub gas: GasPolicy,
+    #[serde(default)]
+    pub fees: FeePolicy,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self::alphanet()
+    }
+}
+
+impl NetworkConfig {
+    pub fn alphanet() -> Self {
+        Self {
+            name: "alphanet".to_string(),
+            rpc_url: WVM_RPC_URL.to_string(),
+            chain_id: CHAIN_ID,
+            babe1: ADDRESS_BABE1.to_string(),
+            babe2: ADDRESS_BABE2.to_string(),
+            gas: GasPolicy::default(),
+            fees: FeePolicy::default(),
+        }
+    }
+
+    /// custom network (devnet, fork, local anvil node) reusing the alphanet
+    /// bundle target addresses and gas settings
+    pub fn custom(rpc_url: String, chain_id: u64) -> Self {
+        Self {
+            name: "custom".to_string(),
+            rpc_url,
+            chain_id,
+            ..Self::alphanet()
+        }
+    }
+
+    pub fn preset(name: &str) -> Result<Self, Error> {
+        match name.to_ascii_lowercase().as_str() {
+            "alphanet" => Ok(Self::alphanet()),
+            other => Err(Error::InvalidNetworkConfig(format!(
+                "unknown network preset: {}",
+                other
+            ))),
+        }
+    }
+
+    pub fn rpc_url(mut self, rpc_url: String) -> Self {
+        self.rpc_url = rpc_url;
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn babe1(mut self, address: String) -> Self {
+        self.babe1 = address;
+        self
+    }
+
+    pub fn babe2(mut self, address: String) -> Self {
+        self.babe2 = address;
+        self
+    }
+
+    pub fn gas(mut self, gas: GasPolicy) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    pub fn fees(mut self, fees: FeePolicy) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    pub fn from_toml_str(input: &str) -> Result<Self, Error> {
+        toml::from_str(input).map_err(|e| Error::InvalidNetworkConfig(e.to_string()))
+    }
+
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::InvalidNetworkConfig(e.to_string()))?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Loads the network profile from the environment (and `.env`):
+    /// - `BUNDLER_NETWORK_CONFIG`: path to a TOML network profile
+    /// - `BUNDLER_NETWORK`: preset name, defaults to `alphanet`
+    /// - `BUNDLER_RPC_URL`, `BUNDLER_CHAIN_ID`, `BUNDLER_BABE1`, `BUNDLER_BABE2`: overrides
+    pub fn from_env() -> Result<Self, Error> {
+        let mut config = match get_env_key("BUNDLER_NETWORK_CONFIG".to_string()) {
+            Ok(path) => Self::from_toml_file(path)?,
+            Err(_) => Self::preset(
+                &get_env_key("BUNDLER_NETWORK".to_string()).unwrap_or("alphanet".to_string()),
+            )?,
+        };
+
+        if let Ok(rpc_url) = get_env_key("BUNDLER_RPC_URL".to_string()) {
+            config.name = "custom".to_string();
+            config.rpc_url = rpc_url;
+        }
+        if let Ok(chain_id) = get_env_key("BUNDLER_CHAIN_ID".to_string()) {
+            config.chain_id = chain_id
+                .parse()
+                .map_err(|_| Error::InvalidNetworkConfig("invalid BUNDLER_CHAIN_ID".to_string()))?;
+        }
+        if let Ok(babe1) = get_env_key("BUNDLER_BABE1".to_string()) {
+            config.babe1 = babe1;
+        }
+        if let Ok(babe2) = get_env_key("BUNDLER_BABE2".to_string()) {
+            config.babe2 = babe2;
+        }
+
+        Ok(config)
+    }
+}
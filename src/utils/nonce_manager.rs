@@ -0,0 +1,150 @@
+use crate::utils::errors::Error;
+use crate::utils::evm::HttpClient;
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+static GLOBAL_NONCE_MANAGER: OnceLock<NonceManager> = OnceLock::new();
+
+#[derive(Debug, Default)]
+struct AccountNonces {
+    // next never handed out nonce
+    next: u64,
+    // handed out, not yet accepted or rejected by the node
+    in_flight: BTreeSet<u64>,
+    // handed out then rejected, reused first so no gap is left behind
+    released: BTreeSet<u64>,
+}
+
+/// Hands out sequential nonces per chain and address, so bundles paid by the
+/// same key can be broadcast concurrently without colliding.
+///
+/// Whenever no nonce of an address is in flight, the next one is read again
+/// from the node's pending transactions count: txs sent by other processes
+/// are accounted for, and dropped txs leave no permanent gap.
+#[derive(Debug, Clone, Default)]
+pub struct NonceManager {
+    accounts: Arc<Mutex<HashMap<(u64, Address), AccountNonces>>>,
+}
+
+/// A nonce handed out by the `NonceManager`. It is released, and handed out
+/// again, when dropped before `confirm`: a broadcast cancelled mid-flight
+/// leaves no gap behind.
+#[derive(Debug)]
+pub struct NonceGuard {
+    manager: NonceManager,
+    chain_id: u64,
+    address: Address,
+    nonce: u64,
+    settled: bool,
+}
+
+impl NonceGuard {
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// The tx using the nonce was accepted by the node.
+    pub fn confirm(mut self) {
+        self.settled = true;
+        self.manager
+            .settle(self.chain_id, self.address, self.nonce, false);
+    }
+
+    /// The tx using the nonce was rejected, the nonce is handed out again.
+    pub fn release(self) {}
+
+    /// The nonce was already used outside the manager: it is dropped and the
+    /// next nonce catches up with the node's pending transactions count.
+    pub async fn resync(mut self, provider: &HttpClient) -> Result<(), Error> {
+        self.settled = true;
+        self.manager
+            .settle(self.chain_id, self.address, self.nonce, false);
+        let pending = provider
+            .get_transaction_count(self.address)
+            .pending()
+            .await?;
+
+        let mut accounts = self.manager.accounts.lock().unwrap();
+        let account = accounts.entry((self.chain_id, self.address)).or_default();
+        account.next = account.next.max(pending);
+        account.released.retain(|released| *released >= pending);
+
+        Ok(())
+    }
+}
+
+impl Drop for NonceGuard {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.manager
+                .settle(self.chain_id, self.address, self.nonce, true);
+        }
+    }
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide manager used by `broadcast_bundle`.
+    pub fn global() -> &'static NonceManager {
+        GLOBAL_NONCE_MANAGER.get_or_init(NonceManager::new)
+    }
+
+    /// Hands out the lowest released nonce, or the next sequential one.
+    pub async fn next(
+        &self,
+        provider: &HttpClient,
+        chain_id: u64,
+        address: Address,
+    ) -> Result<NonceGuard, Error> {
+        let idle = |manager: &Self| {
+            let accounts = manager.accounts.lock().unwrap();
+            accounts
+                .get(&(chain_id, address))
+                .is_none_or(|account| account.in_flight.is_empty())
+        };
+        let pending = match idle(self) {
+            true => Some(provider.get_transaction_count(address).pending().await?),
+            false => None,
+        };
+
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry((chain_id, address)).or_default();
+
+        // a concurrent caller may have handed out nonces during the fetch
+        if let Some(pending) = pending.filter(|_| account.in_flight.is_empty()) {
+            account.next = pending;
+            account.released.clear();
+        }
+
+        let nonce = match account.released.pop_first() {
+            Some(nonce) => nonce,
+            None => {
+                account.next += 1;
+                account.next - 1
+            }
+        };
+        account.in_flight.insert(nonce);
+
+        Ok(NonceGuard {
+            manager: self.clone(),
+            chain_id,
+            address,
+            nonce,
+            settled: false,
+        })
+    }
+
+    fn settle(&self, chain_id: u64, address: Address, nonce: u64, released: bool) {
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(account) = accounts.get_mut(&(chain_id, address)) {
+            if account.in_flight.remove(&nonce) && released {
+                account.released.insert(nonce);
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use alloy::primitives::{Address, ChainId, B256};
+use alloy::signers::{Signature, Signer};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignHashRequest {
+    pub address: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignHashResponse {
+    // 65 bytes r || s || v, hex encoded
+    pub signature: String,
+}
+
+/// Signer delegating `sign_hash` to an HTTP service: `POST {url}/sign` with a
+/// `SignHashRequest` body, answered by a `SignHashResponse`.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    pub url: String,
+    pub address: Address,
+    pub chain_id: Option<ChainId>,
+    client: Client,
+}
+
+impl RemoteSigner {
+    pub fn new(url: String, address: Address) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            address,
+            chain_id: None,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    async fn sign_hash(&self, hash: &B256) -> alloy::signers::Result<Signature> {
+        let request = SignHashRequest {
+            address: self.address.to_string(),
+            hash: hash.to_string(),
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/sign", self.url))
+            .json(&request)
+            .send()
+            .await
+            .map_err(alloy::signers::Error::other)?;
+
+        if !response.status().is_success() {
+            return Err(alloy::signers::Error::other(format!(
+                "remote signer responded with {}",
+                response.status()
+            )));
+        }
+
+        let response: SignHashResponse = response
+            .json()
+            .await
+            .map_err(alloy::signers::Error::other)?;
+        let bytes = hex::decode(response.signature.trim_start_matches("0x"))
+            .map_err(alloy::signers::Error::other)?;
+
+        Signature::try_from(bytes.as_slice()).map_err(alloy::signers::Error::other)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
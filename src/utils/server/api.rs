@@ -1,202 +1,438 @@
-use crate::utils::constants::{ADDRESS_BABE1, ADDRESS_BABE2};
+use crate::utils::constants::{LB_CHUNK_MAX_SIZE, LB_PROPAGATE_MAX_CONCURRENT, TAGS_SIZE_LIMIT};
 use crate::utils::core::bundle::Bundle;
 use crate::utils::core::bundle_data::BundleData;
+use crate::utils::core::bundle_validator::BundleValidator;
+use crate::utils::core::envelope::Envelope;
 use crate::utils::core::large_bundle::LargeBundle;
+use crate::utils::core::tags::Tag;
+use crate::utils::core::tx_envelope_writer::TxEnvelopeWrapper;
+use crate::utils::core::verified_bundle::{VerificationMode, VerifiedBundleData};
 use crate::utils::errors::Error;
+use crate::utils::server::range::{
+    multipart_segments, parse_range_header, range_segments, ByteRange, RangeRequest, Segment,
+};
+use crate::utils::server::state::AppState;
+use alloy::primitives::{keccak256, Address};
 use axum::body::Body;
-use axum::response::IntoResponse;
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
 use axum::{extract::Path, response::Json};
 use bytes::Bytes;
-use futures::stream::{self};
+use futures::stream::{self, StreamExt};
+use reqwest::header::HeaderMap;
 use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::convert::Infallible;
+use std::sync::Arc;
 
 pub async fn get_greet() -> &'static str {
     "running UwU"
 }
 
-pub async fn get_envelopes_of(Path(id): Path<String>) -> Json<Value> {
-    let envelopes: BundleData = Bundle::retrieve_envelopes(id, ADDRESS_BABE1).await.unwrap();
-    Json(serde_json::to_value(&envelopes).unwrap())
+#[derive(Debug, Deserialize)]
+pub struct VerifyQuery {
+    // `?verify=reject` or `?verify=flag`, no envelope verification when absent
+    pub verify: Option<VerificationMode>,
 }
 
-pub async fn get_envelopes_of_full(Path(id): Path<String>) -> Json<Value> {
-    let envelopes: BundleData = Bundle::retrieve_envelopes(id, ADDRESS_BABE1).await.unwrap();
-    let envelopes_with_owners = BundleData::to_bundle_with_owners(envelopes).await;
-    Json(serde_json::to_value(&envelopes_with_owners).unwrap())
+fn envelopes_response(
+    envelopes: BundleData,
+    verify: Option<VerificationMode>,
+) -> Result<Json<Value>, Error> {
+    match verify {
+        Some(mode) => {
+            let verified = VerifiedBundleData::verify(envelopes, mode)?;
+            Ok(Json(serde_json::to_value(&verified).unwrap()))
+        }
+        None => Ok(Json(serde_json::to_value(&envelopes).unwrap())),
+    }
+}
+
+pub async fn get_envelopes_of(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<VerifyQuery>,
+) -> Result<Json<Value>, Error> {
+    let envelopes: BundleData =
+        Bundle::retrieve_envelopes_with_network(id, &state.network.babe1, &state.network).await?;
+    envelopes_response(envelopes, query.verify)
 }
 
-pub async fn get_envelopes_id_of(Path(id): Path<String>) -> Json<Value> {
-    let envelopes: BundleData = Bundle::retrieve_envelopes(id, ADDRESS_BABE2).await.unwrap();
+pub async fn get_envelopes_of_full(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, Error> {
+    let envelopes: BundleData =
+        Bundle::retrieve_envelopes_with_network(id, &state.network.babe1, &state.network).await?;
+    let envelopes_with_owners = BundleData::to_bundle_with_owners(envelopes).await?;
+    Ok(Json(serde_json::to_value(&envelopes_with_owners).unwrap()))
+}
+
+pub async fn get_envelopes_id_of(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, Error> {
+    let envelopes: BundleData =
+        Bundle::retrieve_envelopes_with_network(id, &state.network.babe2, &state.network).await?;
     let envelopes_ids: Vec<String> = envelopes.envelopes.into_iter().map(|tx| tx.hash).collect();
-    Json(serde_json::to_value(&envelopes_ids).unwrap())
+    Ok(Json(serde_json::to_value(&envelopes_ids).unwrap()))
 }
 
-pub async fn get_envelopes_of_2(Path(id): Path<String>) -> Json<Value> {
-    let envelopes: BundleData = Bundle::retrieve_envelopes(id, ADDRESS_BABE2).await.unwrap();
-    Json(serde_json::to_value(&envelopes).unwrap())
+pub async fn get_envelopes_of_2(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<VerifyQuery>,
+) -> Result<Json<Value>, Error> {
+    let envelopes: BundleData =
+        Bundle::retrieve_envelopes_with_network(id, &state.network.babe2, &state.network).await?;
+    envelopes_response(envelopes, query.verify)
 }
 
-pub async fn get_envelopes_of_full_2(Path(id): Path<String>) -> Json<Value> {
-    let envelopes: BundleData = Bundle::retrieve_envelopes(id, ADDRESS_BABE2).await.unwrap();
-    let envelopes_with_owners = BundleData::to_bundle_with_owners(envelopes).await;
-    Json(serde_json::to_value(&envelopes_with_owners).unwrap())
+pub async fn get_envelopes_of_full_2(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, Error> {
+    let envelopes: BundleData =
+        Bundle::retrieve_envelopes_with_network(id, &state.network.babe2, &state.network).await?;
+    let envelopes_with_owners = BundleData::to_bundle_with_owners(envelopes).await?;
+    Ok(Json(serde_json::to_value(&envelopes_with_owners).unwrap()))
 }
 
-pub async fn get_envelopes_id_of_2(Path(id): Path<String>) -> Json<Value> {
-    let envelopes: BundleData = Bundle::retrieve_envelopes(id, ADDRESS_BABE2).await.unwrap();
+pub async fn get_envelopes_id_of_2(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, Error> {
+    let envelopes: BundleData =
+        Bundle::retrieve_envelopes_with_network(id, &state.network.babe2, &state.network).await?;
     let envelopes_ids: Vec<String> = envelopes.envelopes.into_iter().map(|tx| tx.hash).collect();
-    Json(serde_json::to_value(&envelopes_ids).unwrap())
+    Ok(Json(serde_json::to_value(&envelopes_ids).unwrap()))
 }
 
-pub async fn resolve_large_bundle(Path(id): Path<String>) -> impl IntoResponse {
-    let large_bundle = match LargeBundle::retrieve_chunks_receipts(id.clone()).await {
-        Ok(bundle) => bundle,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error retrieving bundle: {}", e),
-            )
-                .into_response();
-        }
+#[derive(Debug, Deserialize)]
+pub struct UploadQuery {
+    // original filename, recorded in the Large Bundle manifest
+    pub filename: Option<String>,
+    // names the upload journal, retrying with the same id resumes the upload
+    pub upload_id: Option<String>,
+}
+
+pub async fn upload_large_bundle(
+    State(state): State<AppState>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<Value>, Error> {
+    let payer = state.payer.clone().ok_or(Error::SubmissionDisabled)?;
+    // a lone payer broadcasts concurrently thanks to the nonce manager
+    let (chunkers, max_concurrent) = if state.chunkers.is_empty() {
+        (vec![payer.clone()], LB_PROPAGATE_MAX_CONCURRENT)
+    } else {
+        let max_concurrent = std::cmp::min(state.chunkers.len(), state.scheduler.max_concurrent);
+        (state.chunkers.clone(), max_concurrent)
     };
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    let size: Option<u64> = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    // chunks recorded by an interrupted upload of the same data are skipped
+    let journal = match (&query.upload_id, size) {
+        (Some(upload_id), _) => Some(upload_id.clone()),
+        (None, Some(size)) => Some(format!(
+            "{}:{}:{}",
+            query.filename.clone().unwrap_or_default(),
+            content_type,
+            size
+        )),
+        (None, None) => None,
+    }
+    .zip(state.journal_dir.as_ref())
+    .map(|(key, dir)| dir.join(format!("{}.journal", keccak256(key))));
+
+    let mut large_bundle = LargeBundle::new()
+        .network((*state.network).clone())
+        .scheduler(state.scheduler.clone())
+        .signer(payer.clone())
+        .payer(payer.clone())
+        .content_type(content_type);
+    if let Some(filename) = query.filename {
+        large_bundle = large_bundle.filename(filename);
+    }
+    if let Some(journal) = journal.as_ref() {
+        large_bundle = large_bundle.journal(journal.to_string_lossy().to_string());
+    }
+
+    // the payer funds the chunkers, sized after the announced body length
+    if !state.chunkers.is_empty() {
+        let addresses: Vec<Address> = chunkers.iter().map(|chunker| chunker.address()).collect();
+        large_bundle
+            .top_up_chunkers(&payer, &addresses, size)
+            .await?;
+    }
+
+    let large_bundle = large_bundle
+        .propagate_stream(body.into_data_stream(), chunkers, max_concurrent)
+        .await?;
+    let chunks_count = large_bundle.chunks_receipts.as_ref().map_or(0, |c| c.len());
+    let large_bundle_txid = large_bundle.finalize().await?;
+    // a finalized upload has nothing left to resume
+    if let Some(journal) = journal {
+        let _ = tokio::fs::remove_file(journal).await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "large_bundle_txid": large_bundle_txid,
+        "chunks": chunks_count,
+    })))
+}
+
+pub async fn validate_bundle(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, Error> {
+    let report = BundleValidator::new(&state.network)
+        .validate_bundle_tx(id, &state.network)
+        .await?;
+    Ok(Json(serde_json::to_value(&report).unwrap()))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmittedEnvelope {
+    // hex encoded envelope data
+    pub data: String,
+    pub target: Option<String>,
+    pub tags: Option<Vec<Tag>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubmitBundleRequest {
+    // raw envelopes, signed by the server payer
+    #[serde(default)]
+    pub envelopes: Vec<SubmittedEnvelope>,
+    // envelopes already signed by their owners
+    #[serde(default)]
+    pub signed_envelopes: Vec<TxEnvelopeWrapper>,
+}
+
+impl SubmittedEnvelope {
+    pub fn into_envelope(self, index: usize) -> Result<Envelope, Error> {
+        let violation = |rule: String| Error::EnvelopeRuleViolation { index, rule };
+
+        let data = alloy::hex::decode(&self.data)?;
+        if data.is_empty() {
+            return Err(violation("envelope data MUST not be empty".to_string()));
+        }
+        if let Some(target) = &self.target {
+            target
+                .parse::<Address>()
+                .map_err(|_| violation(format!("invalid target address {}", target)))?;
+        }
+        let tags_size =
+            serde_json::to_string(&self.tags.clone().unwrap_or_default()).map_or(0, |t| t.len());
+        if tags_size > TAGS_SIZE_LIMIT {
+            return Err(violation(format!(
+                "tags MUST be <= {} bytes (found {})",
+                TAGS_SIZE_LIMIT, tags_size
+            )));
+        }
+
+        Ok(Envelope::new()
+            .data(Some(data))
+            .target(self.target)
+            .tags(self.tags))
+    }
+}
+
+pub async fn submit_bundle(
+    State(state): State<AppState>,
+    Json(request): Json<SubmitBundleRequest>,
+) -> Result<Json<Value>, Error> {
+    let payer = state.payer.clone().ok_or(Error::SubmissionDisabled)?;
+
+    let envelopes = request
+        .envelopes
+        .into_iter()
+        .enumerate()
+        .map(|(index, envelope)| envelope.into_envelope(index))
+        .collect::<Result<Vec<Envelope>, Error>>()?;
+    let envelopes_count = envelopes.len() + request.signed_envelopes.len();
+
+    let bundle_txid = Bundle::new()
+        .network((*state.network).clone())
+        .signer(payer.clone())
+        .payer(payer)
+        .envelopes(envelopes)
+        .signed_envelopes(request.signed_envelopes)
+        .build()?
+        // read back through the /v2 routes, which check against babe2
+        .propagate_to(&state.network.babe2)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "bundle_txid": bundle_txid,
+        "envelopes": envelopes_count,
+    })))
+}
+
+pub async fn resolve_large_bundle(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    match resolve_large_bundle_ranges(state, id, headers).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn resolve_large_bundle_ranges(
+    state: AppState,
+    id: String,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let network = state.network.clone();
+    let large_bundle = LargeBundle::retrieve_chunks_receipts_with_network(id, &network).await?;
 
     let content_type = large_bundle
         .content_type
         .clone()
+        .filter(|c| !c.is_empty())
         .unwrap_or_else(|| "application/octet-stream".to_string());
+    let chunks_receipts = large_bundle
+        .chunks_receipts
+        .filter(|receipts| !receipts.is_empty())
+        .ok_or(Error::LargeBundleChunksRetrieval)?;
 
-    // Get the chunk receipts
-    let chunks_receipts = match &large_bundle.chunks_receipts {
-        Some(receipts) => receipts.clone(),
+    // v2 manifests record the sizes; for v1 every chunk but the last one is
+    // LB_CHUNK_MAX_SIZE long, so the last chunk gives the total size and is
+    // kept to not fetch it twice
+    let last_index = chunks_receipts.len() - 1;
+    let (chunk_size, total_size, last_chunk) = match &large_bundle.manifest {
+        Some(manifest) => (manifest.chunk_size.max(1), manifest.total_size, None),
         None => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "No chunk receipts found".to_string(),
-            )
-                .into_response();
+            let chunk_size = LB_CHUNK_MAX_SIZE as u64;
+            let last_chunk = Bytes::from(
+                LargeBundle::retrieve_chunk(
+                    last_index,
+                    &chunks_receipts[last_index],
+                    None,
+                    &network,
+                )
+                .await?,
+            );
+            let total_size = last_index as u64 * chunk_size + last_chunk.len() as u64;
+            (chunk_size, total_size, Some(last_chunk))
         }
     };
 
-    if chunks_receipts.is_empty() {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Bundle has no chunks".to_string(),
-        )
-            .into_response();
-    }
+    let range_request = match headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        Some(range) if total_size > 0 => parse_range_header(range, total_size),
+        _ => RangeRequest::Full,
+    };
 
-    // For MP4 files, we need to ensure we send content-length and avoid chunked encoding
-    // We'll calculate the total size by fetching metadata about each chunk first
-    let is_mp4 = content_type == "video/mp4" || content_type.starts_with("video/");
+    let response = Response::builder()
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, "public, max-age=31536000");
 
-    if is_mp4 {
-        // For MP4 files, we need to ensure we can use content-length
-        // We'll create a stream that loads chunks one by one but doesn't use chunked encoding
+    let (response, segments) = match range_request {
+        RangeRequest::Unsatisfiable => {
+            return Ok(response
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total_size))
+                .body(Body::empty())
+                .unwrap());
+        }
+        RangeRequest::Full => {
+            let full = ByteRange {
+                start: 0,
+                end: total_size.saturating_sub(1),
+            };
+            let segments = if total_size == 0 {
+                Vec::new()
+            } else {
+                range_segments(&full, chunk_size)
+            };
+            (
+                response
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, content_type),
+                segments,
+            )
+        }
+        RangeRequest::Partial(ranges) if ranges.len() == 1 => (
+            response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_TYPE, content_type)
+                .header(header::CONTENT_RANGE, ranges[0].content_range(total_size)),
+            range_segments(&ranges[0], chunk_size),
+        ),
+        RangeRequest::Partial(ranges) => {
+            let boundary = format!("{:016x}", rand::random::<u64>());
+            let segments =
+                multipart_segments(&ranges, total_size, chunk_size, &content_type, &boundary);
+            (
+                response.status(StatusCode::PARTIAL_CONTENT).header(
+                    header::CONTENT_TYPE,
+                    format!("multipart/byteranges; boundary={}", boundary),
+                ),
+                segments,
+            )
+        }
+    };
 
-        // First, create a streaming body that processes chunks sequentially
-        let stream = stream::unfold(
-            (0, chunks_receipts.clone()),
-            move |(chunk_index, chunks_receipts)| async move {
-                if chunk_index >= chunks_receipts.len() {
-                    return None;
-                }
+    let content_length: u64 = segments.iter().map(Segment::size).sum();
+    let manifest = large_bundle.manifest.map(Arc::new);
 
-                // Get the chunk receipt at the current index
-                let chunk_receipt = &chunks_receipts[chunk_index];
-
-                // Retrieve this chunk's data
-                match retrieve_chunk_data(chunk_receipt).await {
-                    Ok(chunk_data) => Some((
-                        Ok::<_, Infallible>(Bytes::from(chunk_data)),
-                        (chunk_index + 1, chunks_receipts),
-                    )),
-                    Err(e) => {
-                        eprintln!("Error retrieving chunk {}: {}", chunk_index, e);
-                        // Skip this chunk and continue
-                        Some((Ok(Bytes::from(vec![])), (chunk_index + 1, chunks_receipts)))
+    // chunks are fetched one at a time, only the ones intersecting the ranges,
+    // and verified; a failing chunk aborts the body instead of corrupting it
+    let stream = stream::iter(segments).then(move |segment| {
+        let chunks_receipts = chunks_receipts.clone();
+        let manifest = manifest.clone();
+        let last_chunk = last_chunk.clone();
+        let network = network.clone();
+        async move {
+            match segment {
+                Segment::Static(bytes) => Ok(bytes),
+                Segment::Chunk { index, start, end } => {
+                    let chunk = match last_chunk {
+                        Some(last_chunk) if index == last_index => last_chunk,
+                        _ => Bytes::from(
+                            LargeBundle::retrieve_chunk(
+                                index,
+                                &chunks_receipts[index],
+                                manifest.as_deref(),
+                                &network,
+                            )
+                            .await?,
+                        ),
+                    };
+                    if chunk.len() < end {
+                        return Err(Error::Other(format!(
+                            "chunk {} is {} bytes, expected at least {}",
+                            index,
+                            chunk.len(),
+                            end
+                        )));
                     }
-                }
-            },
-        );
-
-        // Create a streaming body
-        let body = Body::from_stream(stream);
-
-        // For MP4 files, it's better to NOT use chunked transfer encoding
-        // But we still want to stream immediately, so we'll use a different approach
-        // We'll let the client know we accept range requests, which is how browsers stream video
-        return axum::response::Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, content_type)
-            .header(header::ACCEPT_RANGES, "bytes")
-            .header(header::CACHE_CONTROL, "public, max-age=31536000")
-            // Don't set Content-Length for streaming
-            .body(body)
-            .unwrap()
-            .into_response();
-    }
-
-    // For non-MP4 files, use normal streaming with chunked encoding
-    let stream = stream::unfold(
-        (0, chunks_receipts),
-        move |(chunk_index, chunks_receipts)| async move {
-            if chunk_index >= chunks_receipts.len() {
-                return None;
-            }
-
-            // Get the chunk receipt at the current index
-            let chunk_receipt = &chunks_receipts[chunk_index];
-
-            // Retrieve this chunk's data
-            match retrieve_chunk_data(chunk_receipt).await {
-                Ok(chunk_data) => Some((
-                    Ok::<_, Infallible>(Bytes::from(chunk_data)),
-                    (chunk_index + 1, chunks_receipts),
-                )),
-                Err(e) => {
-                    eprintln!("Error retrieving chunk {}: {}", chunk_index, e);
-                    Some((Ok(Bytes::from(vec![])), (chunk_index + 1, chunks_receipts)))
+                    Ok(chunk.slice(start..end))
                 }
             }
-        },
-    );
-
-    // Create a streaming body
-    let body = Body::from_stream(stream);
-
-    // Return response with chunked transfer encoding
-    axum::response::Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, content_type)
-        .header(header::TRANSFER_ENCODING, "chunked")
-        .body(body)
-        .unwrap()
-        .into_response()
-}
-
-// Helper function to retrieve a single chunk's data
-async fn retrieve_chunk_data(chunk_receipt: &str) -> Result<Vec<u8>, Error> {
-    // Retrieve the bundle for this chunk
-    let receipt_bundle = Bundle::retrieve_envelopes(chunk_receipt.to_string(), ADDRESS_BABE2)
-        .await
-        .map_err(|e| {
-            Error::Other(format!(
-                "Failed to retrieve bundle for receipt {}: {}",
-                chunk_receipt, e
-            ))
-        })?;
-
-    // Get the envelope data
-    let receipt_writer = receipt_bundle
-        .envelopes
-        .get(0)
-        .ok_or_else(|| Error::Other("Error: no envelopes found".to_string()))?;
-
-    // Decode the chunk data
-    let receipt_data = hex::decode(&receipt_writer.input.trim_start_matches("0x"))
-        .map_err(|e| Error::Other(e.to_string()))?;
+        }
+    });
+    let stream = stream.map(|result: Result<Bytes, Error>| {
+        result.map_err(|e| {
+            eprintln!("Error resolving Large Bundle: {}", e);
+            std::io::Error::other(e.to_string())
+        })
+    });
 
-    Ok(receipt_data)
+    Ok(response
+        .header(header::CONTENT_LENGTH, content_length)
+        .body(Body::from_stream(stream))
+        .unwrap())
 }
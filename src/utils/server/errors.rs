@@ -0,0 +1,41 @@
+use crate::utils::errors::Error;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use reqwest::StatusCode;
+
+impl Error {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            // malformed request input
+            Error::HexError(_) | Error::UnverifiedAddress | Error::EnvelopesNeeded => {
+                StatusCode::BAD_REQUEST
+            }
+            Error::BundleRetrievalProblem => StatusCode::NOT_FOUND,
+            Error::BundleTooLarge(_) | Error::LargeBundleTooLarge(_) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            // the bundle exists but its content breaks the spec
+            Error::InvalidBundleHex(_)
+            | Error::BrotliDecompression(_)
+            | Error::BorshDeserialization(_)
+            | Error::EnvelopeRuleViolation { .. }
+            | Error::EnvelopeVerification(_)
+            | Error::ChunkIntegrity { .. }
+            | Error::ManifestIntegrity(_)
+            | Error::LargeBundleHashMismatch { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            // the bundle costs more than the server's fee policy allows
+            Error::FeeCapExceeded { .. } => StatusCode::PAYMENT_REQUIRED,
+            Error::RpcTransportError(_) => StatusCode::BAD_GATEWAY,
+            // no payer key configured, or no chunker able to pay
+            Error::SubmissionDisabled | Error::NoFundedChunkers => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.to_string() });
+        (self.status_code(), Json(body)).into_response()
+    }
+}
@@ -1 +1,54 @@
 pub mod api;
+pub mod errors;
+pub mod range;
+pub mod state;
+
+use crate::utils::constants::SUBMISSION_BODY_LIMIT;
+use crate::utils::server::api::{
+    get_envelopes_id_of, get_envelopes_id_of_2, get_envelopes_of, get_envelopes_of_2,
+    get_envelopes_of_full, get_envelopes_of_full_2, get_greet, resolve_large_bundle, submit_bundle,
+    upload_large_bundle, validate_bundle,
+};
+use crate::utils::server::state::AppState;
+use axum::extract::DefaultBodyLimit;
+use axum::routing::{get, post};
+use axum::Router;
+use std::time::Duration;
+use tower_http::timeout::TimeoutLayer;
+
+pub fn router(state: AppState) -> Router {
+    let timeout_layer = TimeoutLayer::new(Duration::from_secs(3600));
+
+    Router::new()
+        .route("/", get(get_greet))
+        // v1 routes
+        .route("/v1/envelopes/{bundle_txid}", get(get_envelopes_of))
+        .route("/v1/envelopes/ids/{bundle_txid}", get(get_envelopes_id_of))
+        .route(
+            "/v1/envelopes-full/{bundle_txid}",
+            get(get_envelopes_of_full),
+        )
+        // v2 routes
+        .route("/v2/envelopes/{bundle_txid}", get(get_envelopes_of_2))
+        .route(
+            "/v2/envelopes/ids/{bundle_txid}",
+            get(get_envelopes_id_of_2),
+        )
+        .route(
+            "/v2/envelopes-full/{bundle_txid}",
+            get(get_envelopes_of_full_2),
+        )
+        .route("/v2/resolve/{large_bundle_txid}", get(resolve_large_bundle))
+        .route("/v2/validate/{bundle_txid}", get(validate_bundle))
+        .route(
+            "/v2/bundles",
+            post(submit_bundle).layer(DefaultBodyLimit::max(SUBMISSION_BODY_LIMIT)),
+        )
+        // streamed, the Large Bundle size limit is enforced while chunking
+        .route(
+            "/v2/large-bundles",
+            post(upload_large_bundle).layer(DefaultBodyLimit::disable()),
+        )
+        .layer(timeout_layer)
+        .with_state(state)
+}
@@ -0,0 +1,152 @@
+use bytes::Bytes;
+use std::ops::RangeInclusive;
+
+// ignore abusive Range headers instead of fetching the same chunks again and again
+pub const MAX_RANGES: usize = 16;
+
+/// Inclusive byte range, as in `Range: bytes=start-end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn size(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    pub fn content_range(&self, total_size: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total_size)
+    }
+
+    /// Indexes of the chunks holding this range.
+    pub fn chunks_span(&self, chunk_size: u64) -> RangeInclusive<usize> {
+        (self.start / chunk_size) as usize..=(self.end / chunk_size) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeRequest {
+    // no usable Range header, the whole content is served
+    Full,
+    Partial(Vec<ByteRange>),
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header against the content size. Malformed headers are
+/// ignored (RFC 9110 14.2), a header without any satisfiable range is not.
+pub fn parse_range_header(value: &str, total_size: u64) -> RangeRequest {
+    let Some(specs) = value.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+
+    let mut ranges = Vec::new();
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((start, end)) = spec.split_once('-') else {
+            return RangeRequest::Full;
+        };
+
+        let range = match (start.trim(), end.trim()) {
+            // suffix range: the last `n` bytes
+            ("", suffix) => match suffix.parse::<u64>() {
+                Ok(0) => None,
+                Ok(n) if total_size > 0 => Some(ByteRange {
+                    start: total_size.saturating_sub(n),
+                    end: total_size - 1,
+                }),
+                Ok(_) => None,
+                Err(_) => return RangeRequest::Full,
+            },
+            (start, end) => {
+                let Ok(start) = start.parse::<u64>() else {
+                    return RangeRequest::Full;
+                };
+                let end = match end {
+                    "" => None,
+                    end => match end.parse::<u64>() {
+                        Ok(end) if end >= start => Some(end),
+                        _ => return RangeRequest::Full,
+                    },
+                };
+                (start < total_size).then(|| ByteRange {
+                    start,
+                    end: end.map_or(total_size - 1, |end| end.min(total_size - 1)),
+                })
+            }
+        };
+        ranges.extend(range);
+    }
+
+    if ranges.len() > MAX_RANGES {
+        return RangeRequest::Full;
+    }
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Partial(ranges)
+}
+
+/// A piece of a response body: literal bytes, or a slice of a chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Static(Bytes),
+    Chunk {
+        index: usize,
+        start: usize,
+        end: usize, // exclusive
+    },
+}
+
+impl Segment {
+    pub fn size(&self) -> u64 {
+        match self {
+            Segment::Static(bytes) => bytes.len() as u64,
+            Segment::Chunk { start, end, .. } => (end - start) as u64,
+        }
+    }
+}
+
+/// Chunk slices covering `range`, all chunks but the last being `chunk_size` long.
+pub fn range_segments(range: &ByteRange, chunk_size: u64) -> Vec<Segment> {
+    range
+        .chunks_span(chunk_size)
+        .map(|index| {
+            let chunk_start = index as u64 * chunk_size;
+            let start = range.start.max(chunk_start) - chunk_start;
+            let end = range.end.min(chunk_start + chunk_size - 1) - chunk_start + 1;
+            Segment::Chunk {
+                index,
+                start: start as usize,
+                end: end as usize,
+            }
+        })
+        .collect()
+}
+
+/// `multipart/byteranges` body segments for several ranges.
+pub fn multipart_segments(
+    ranges: &[ByteRange],
+    total_size: u64,
+    chunk_size: u64,
+    content_type: &str,
+    boundary: &str,
+) -> Vec<Segment> {
+    let mut segments = Vec::new();
+
+    for range in ranges {
+        segments.push(Segment::Static(Bytes::from(format!(
+            "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            boundary,
+            content_type,
+            range.content_range(total_size)
+        ))));
+        segments.extend(range_segments(range, chunk_size));
+    }
+    segments.push(Segment::Static(Bytes::from(format!(
+        "\r\n--{}--\r\n",
+        boundary
+    ))));
+
+    segments
+}
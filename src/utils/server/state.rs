@@ -0,0 +1,119 @@
+use crate::utils::core::chunker_scheduler::SchedulerPolicy;
+use crate::utils::core::super_account::SuperAccount;
+use crate::utils::env_var::get_env_key;
+use crate::utils::errors::Error;
+use crate::utils::network::NetworkConfig;
+use crate::utils::signer::BundlerSigner;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct AppState {
+    pub network: Arc<NetworkConfig>,
+    // signs and pays for submitted bundles, submission is disabled without it
+    pub payer: Option<BundlerSigner>,
+    // propagate Large Bundle chunks, the payer is used when empty
+    pub chunkers: Vec<BundlerSigner>,
+    pub scheduler: SchedulerPolicy,
+    // Large Bundle upload journals, so an interrupted upload can be resumed
+    pub journal_dir: Option<PathBuf>,
+}
+
+impl AppState {
+    pub fn new(network: NetworkConfig) -> Self {
+        Self {
+            network: Arc::new(network),
+            payer: None,
+            chunkers: Vec::new(),
+            scheduler: SchedulerPolicy::default(),
+            journal_dir: Some(std::env::temp_dir().join("bundler-journals")),
+        }
+    }
+
+    pub fn payer(mut self, payer: BundlerSigner) -> Self {
+        self.payer = Some(payer);
+        self
+    }
+
+    pub fn chunkers(mut self, chunkers: Vec<BundlerSigner>) -> Self {
+        self.chunkers = chunkers;
+        self
+    }
+
+    pub fn scheduler(mut self, scheduler: SchedulerPolicy) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    pub fn journal_dir(mut self, journal_dir: Option<PathBuf>) -> Self {
+        self.journal_dir = journal_dir;
+        self
+    }
+
+    /// Keeps the upload journals in `BUNDLER_JOURNAL_DIR` instead of the
+    /// system temp directory.
+    pub fn journal_dir_from_env(self) -> Self {
+        match get_env_key("BUNDLER_JOURNAL_DIR".to_string()) {
+            Ok(dir) => self.journal_dir(Some(PathBuf::from(dir))),
+            Err(_) => self,
+        }
+    }
+
+    /// Loads the payer from `BUNDLER_PAYER_PRIVATE_KEY`, or from
+    /// `BUNDLER_PAYER_KEYSTORE` and `BUNDLER_PAYER_KEYSTORE_PASSWORD`.
+    pub fn payer_from_env(self) -> Result<Self, Error> {
+        if let Ok(private_key) = get_env_key("BUNDLER_PAYER_PRIVATE_KEY".to_string()) {
+            return Ok(self.payer(BundlerSigner::from_private_key(&private_key)?));
+        }
+        if let Ok(keystore) = get_env_key("BUNDLER_PAYER_KEYSTORE".to_string()) {
+            let pwd = get_env_key("BUNDLER_PAYER_KEYSTORE_PASSWORD".to_string())
+                .map_err(|_| Error::InvalidKeystore)?;
+            return Ok(self.payer(BundlerSigner::from_keystore(keystore, &pwd)?));
+        }
+        Ok(self)
+    }
+
+    /// Derives the Large Bundle chunkers from `BUNDLER_CHUNKERS_MNEMONIC`, at
+    /// `BUNDLER_CHUNKERS_DERIVATION_PATH` for the first `BUNDLER_CHUNKERS_COUNT`
+    /// indexes, or loads those of the SuperAccount keystore at
+    /// `BUNDLER_CHUNKERS_KEYSTORE`, encrypted with `BUNDLER_CHUNKERS_KEYSTORE_PASSWORD`.
+    /// `BUNDLER_CHUNKERS_MAX_CONCURRENT` caps the chunks they send at once.
+    pub async fn chunkers_from_env(mut self) -> Result<Self, Error> {
+        if let Some(max_concurrent) = Self::count_from_env("BUNDLER_CHUNKERS_MAX_CONCURRENT")? {
+            self.scheduler = self.scheduler.max_concurrent(max_concurrent);
+        }
+        let mut super_account = SuperAccount::new().network((*self.network).clone());
+
+        if let Ok(mnemonic) = get_env_key("BUNDLER_CHUNKERS_MNEMONIC".to_string()) {
+            super_account = super_account.mnemonic(mnemonic);
+            if let Ok(path) = get_env_key("BUNDLER_CHUNKERS_DERIVATION_PATH".to_string()) {
+                super_account = super_account.derivation_path(path);
+            }
+        } else if let Ok(keystore) = get_env_key("BUNDLER_CHUNKERS_KEYSTORE".to_string()) {
+            let pwd = get_env_key("BUNDLER_CHUNKERS_KEYSTORE_PASSWORD".to_string())
+                .map_err(|_| Error::InvalidKeystore)?;
+            super_account = super_account.keystore_path(keystore).pwd(pwd);
+        } else {
+            return Ok(self);
+        }
+
+        let count = Self::count_from_env("BUNDLER_CHUNKERS_COUNT")?;
+        let chunkers = super_account
+            .load_chunkers(count)
+            .await?
+            .chunkers
+            .unwrap_or_default();
+
+        Ok(self.chunkers(chunkers.into_iter().map(BundlerSigner::new).collect()))
+    }
+
+    fn count_from_env<T: std::str::FromStr>(key: &str) -> Result<Option<T>, Error> {
+        match get_env_key(key.to_string()) {
+            Ok(count) => count
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::Other(format!("{} is not a number: {}", key, count))),
+            Err(_) => Ok(None),
+        }
+    }
+}
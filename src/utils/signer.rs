@@ -0,0 +1,105 @@
+use crate::utils::errors::Error;
+use crate::utils::remote_signer::RemoteSigner;
+use alloy::consensus::SignableTransaction;
+use alloy::network::{EthereumWallet, TxSigner};
+use alloy::primitives::{Address, B256};
+use alloy::signers::local::coins_bip39::English;
+use alloy::signers::local::{LocalSigner, MnemonicBuilder, PrivateKeySigner};
+use alloy::signers::{Signature, Signer};
+use async_trait::async_trait;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Signs envelopes and bundle transactions with any alloy `Signer`: a local
+/// key, a keystore file, a mnemonic-derived key or a remote HTTP signer.
+#[derive(Clone)]
+pub struct BundlerSigner {
+    inner: Arc<dyn Signer + Send + Sync>,
+}
+
+impl fmt::Debug for BundlerSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BundlerSigner")
+            .field("address", &self.address())
+            .finish()
+    }
+}
+
+impl BundlerSigner {
+    pub fn new<S: Signer + Send + Sync + 'static>(signer: S) -> Self {
+        Self {
+            inner: Arc::new(signer),
+        }
+    }
+
+    pub fn from_private_key(private_key: &str) -> Result<Self, Error> {
+        let signer: PrivateKeySigner = private_key
+            .parse()
+            .map_err(|_| Error::PrivateKeyParsingError)?;
+        Ok(Self::new(signer))
+    }
+
+    pub fn from_keystore<P: AsRef<Path>>(path: P, pwd: &str) -> Result<Self, Error> {
+        let signer =
+            LocalSigner::decrypt_keystore(path, pwd).map_err(|_| Error::InvalidKeystore)?;
+        Ok(Self::new(signer))
+    }
+
+    /// Derives the key at `m/44'/60'/0'/0/{index}` from a BIP-39 mnemonic.
+    pub fn from_mnemonic(phrase: &str, index: u32) -> Result<Self, Error> {
+        let signer = MnemonicBuilder::<English>::default()
+            .phrase(phrase)
+            .index(index)?
+            .build()?;
+        Ok(Self::new(signer))
+    }
+
+    pub fn from_mnemonic_path(phrase: &str, derivation_path: &str) -> Result<Self, Error> {
+        let signer = MnemonicBuilder::<English>::default()
+            .phrase(phrase)
+            .derivation_path(derivation_path)?
+            .build()?;
+        Ok(Self::new(signer))
+    }
+
+    pub fn remote(url: String, address: Address) -> Self {
+        Self::new(RemoteSigner::new(url, address))
+    }
+
+    pub fn address(&self) -> Address {
+        self.inner.address()
+    }
+
+    pub async fn sign_hash(&self, hash: &B256) -> Result<Signature, Error> {
+        self.inner
+            .sign_hash(hash)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))
+    }
+
+    pub async fn sign_message(&self, message: &[u8]) -> Result<Signature, Error> {
+        self.inner
+            .sign_message(message)
+            .await
+            .map_err(|e| Error::Other(e.to_string()))
+    }
+
+    pub fn wallet(&self) -> EthereumWallet {
+        EthereumWallet::from(self.clone())
+    }
+}
+
+#[async_trait]
+impl TxSigner<Signature> for BundlerSigner {
+    fn address(&self) -> Address {
+        self.inner.address()
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        self.inner.sign_hash(&tx.signature_hash()).await
+    }
+}